@@ -0,0 +1,170 @@
+# Deutsch. Muss dieselben Schlüssel wie en.ftl haben.
+
+## Menu
+
+menu-enable = Lapsus aktivieren
+menu-disable = Lapsus deaktivieren
+menu-pause-for = Pausieren für
+menu-pause-minutes = { $minutes } Minuten
+menu-pause-hour = 1 Stunde
+menu-start-at-login = Beim Anmelden starten
+menu-preferences = Einstellungen…
+menu-statistics = Statistik
+menu-log-level = Protokollstufe
+menu-log-error = Fehler
+menu-log-warn = Warnungen
+menu-log-info = Info
+menu-log-debug = Debug
+menu-log-trace = Trace
+menu-about = Über
+menu-quit = Beenden
+menu-quit-leave-running = Beenden (Lapsus weiterlaufen lassen)
+menu-quit-stop = Beenden und Lapsus stoppen
+
+## Status header and notes
+
+status-stopped = Gestoppt
+status-running = Läuft · PID { $pid }
+status-running-stats = Läuft · PID { $pid } · seit { $uptime } · CPU { $cpu } % · { $memory }
+pause-label = Pausiert, weiter in { $minutes } Min.
+rule-disabled = Deaktiviert, solange { $app } aktiv ist
+rule-enabled = Aktiviert, solange { $app } aktiv ist
+schedule-on = Zeitplan: an bis { $end }
+schedule-off = Zeitplan: aus bis { $end }
+schedule-overridden = { $label } (übersteuert)
+power-low-power-mode = Stromsparmodus
+power-battery = Akku bei { $percent } %
+power-disabled = Deaktiviert: { $reason }
+power-light-profile = Sparprofil: { $reason }
+
+## Dialogs
+
+dialog-about-title = Über
+dialog-about-version = { $app } – Version { $version }
+dialog-about-description = Menüleisten-App zur Steuerung von lapsus_rust
+dialog-error-title = Fehler
+dialog-choose-binary = lapsus_rust auswählen
+
+## Notifications
+
+notify-error-title = Lapsus-Fehler
+notify-crash-title = Lapsus abgestürzt
+notify-suppressed = { $body } ({ $count } ähnliche Mitteilungen unterdrückt)
+state-rule-enabled = Lapsus aktiviert: zu { $app } gewechselt
+state-rule-disabled = Lapsus deaktiviert: zu { $app } gewechselt
+state-schedule-enabled = Lapsus nach Zeitplan aktiviert
+state-schedule-disabled = Lapsus nach Zeitplan deaktiviert
+state-power-lifted = Energierichtlinie aufgehoben, lapsus_rust läuft wieder normal
+state-pause-ended = Pause beendet, lapsus_rust läuft wieder
+crash-restarted = lapsus_rust wurde unerwartet beendet und neu gestartet
+crash-restart-failed = lapsus_rust wurde unerwartet beendet und konnte nicht neu gestartet werden: { $error }
+crash-gave-up = lapsus_rust stürzt wiederholt ab und bleibt deshalb gestoppt. Über das Menü kann es erneut aktiviert werden
+health-stopped = lapsus_rust ist zu lange angehalten
+health-cpu = lapsus_rust nutzt seit { $duration } { $cpu }% CPU
+health-memory = Der Speicher von lapsus_rust ist um { $growth } MB gewachsen
+health-liveness = lapsus_rust aktualisiert { $file } nicht mehr
+health-note = Gestört: { $problem }
+health-restarted = { $problem }, daher wurde es neu gestartet
+health-restart-failed = { $problem }, und es konnte nicht neu gestartet werden: { $error }
+health-gave-up = { $problem }. Es wurde zuletzt zu oft neu gestartet und bleibt daher, wie es ist
+health-unhealthy = { $problem }
+
+## Errors
+
+error-already-running = Lapsus Control läuft bereits und ist nicht erreichbar: { $error }
+error-init = App konnte nicht gestartet werden: { $error }
+error-not-found = lapsus_rust nicht gefunden unter: { $path }. Bitte sicherstellen, dass lapsus_rust am richtigen Ort liegt.
+error-not-found-anywhere = lapsus_rust wurde an keinem der üblichen Orte gefunden. Bitte den Pfad in den Einstellungen festlegen oder es mit der App ausliefern.
+error-hotkey = Tastenkürzel zum Umschalten konnte nicht eingerichtet werden: { $error }
+error-start = lapsus_rust konnte nicht gestartet werden: { $error }
+error-stop = lapsus_rust konnte nicht gestoppt werden: { $error }
+error-pause = lapsus_rust konnte nicht pausiert werden: { $error }
+error-restart = lapsus_rust konnte nicht neu gestartet werden: { $error }
+error-resume = lapsus_rust konnte nicht fortgesetzt werden: { $error }
+error-auto-launch = Start beim Anmelden konnte nicht geändert werden: { $error }
+error-open-preferences = Einstellungen konnten nicht geöffnet werden: { $error }
+error-save-preferences = Einstellungen konnten nicht gespeichert werden: { $error }
+error-lapsus-path = Der neue Pfad zu lapsus_rust kann nicht verwendet werden: { $error }
+error-daemon-login = Start von lapsus_rust beim Anmelden konnte nicht geändert werden: { $error }
+error-rule = Regel für { $app } fehlgeschlagen: { $error }
+error-session = Verarbeitung von { $event } fehlgeschlagen: { $error }
+error-schedule = Geplante Änderung fehlgeschlagen: { $error }
+error-power = Änderung durch Energierichtlinie fehlgeschlagen: { $error }
+error-metrics = Metriken können nicht auf Port { $port } bereitgestellt werden: { $error }
+
+## Hotkeys
+
+hotkey-invalid = ungültiges Tastenkürzel: { $reason }
+hotkey-no-modifier = Tastenkürzel „{ $hotkey }“ braucht Control, Option/Alt oder Befehl/Super
+hotkey-reserved = Tastenkürzel „{ $hotkey }“ ist bereits belegt: { $used_for }
+shortcut-spotlight = Spotlight / Wechsel der Eingabequelle
+shortcut-input-source = Wechsel der Eingabequelle
+shortcut-app-switcher = App-Umschalter
+shortcut-window-switcher = Fensterumschalter
+shortcut-quit = Beenden
+shortcut-close-window = Fenster schließen
+shortcut-hide = Ausblenden
+shortcut-minimize = Im Dock ablegen
+shortcut-select-all = Alles auswählen
+shortcut-copy = Kopieren
+shortcut-paste = Einsetzen
+shortcut-cut = Ausschneiden
+shortcut-undo = Widerrufen
+shortcut-save = Sichern
+shortcut-interrupt = Kopieren / Abbrechen
+shortcut-screenshot = Bildschirmfoto
+shortcut-force-quit = Sofort beenden
+shortcut-lock-screen = Bildschirm sperren
+shortcut-system-menu = Systemmenü
+shortcut-terminal = Terminal-Kürzel
+
+## Settings checks
+
+config-no-file = { $setting }: keine Datei unter { $path }
+config-no-directory = { $setting }: kein Ordner unter { $path }
+config-bad-color = { $setting }: „{ $color }“ ist keine Farbe (#RRGGBB, #RGB oder ein Name wie orange)
+config-bad-threshold = { $setting }: battery_threshold { $threshold } liegt über 100 %
+config-problem = { $setting }: { $problem }
+schedule-invalid-day = ungültiger Tag: { $day }
+schedule-invalid-time = ungültige Uhrzeit: { $time }
+schedule-empty = leeres Zeitfenster: { $start }–{ $end }
+
+## Preferences window
+
+prefs-title = { $app } – Einstellungen
+prefs-binary-section = lapsus_rust
+prefs-binary = Programm
+prefs-binary-placeholder = An den üblichen Orten suchen
+prefs-browse = Auswählen…
+prefs-args = Startargumente, eines pro Zeile
+prefs-args-hint = Gilt, wenn die App lapsus_rust selbst startet, nicht über einen LaunchAgent.
+prefs-behavior = Verhalten
+prefs-start-at-login = Menüleisten-App beim Anmelden starten
+prefs-daemon-at-login = lapsus_rust beim Anmelden starten
+prefs-restore-state = Beim Start den letzten Zustand wiederherstellen
+prefs-quit = Beim Beenden der Menüleisten-App
+prefs-quit-stop = lapsus_rust beenden
+prefs-quit-leave-running = lapsus_rust weiterlaufen lassen
+prefs-display = Wenn der Zeiger auf einen anderen Bildschirm wechselt
+prefs-display-restart = lapsus_rust neu starten
+prefs-display-nothing = Nichts tun
+prefs-hotkey = Tastenkürzel zum Umschalten
+prefs-hotkey-placeholder = z. B. cmd+shift+l
+prefs-notifications = Mitteilungen
+prefs-notify-errors = Fehler
+prefs-notify-crashes = Abstürze
+prefs-notify-changes = Automatisches Aktivieren/Deaktivieren
+prefs-cancel = Abbrechen
+prefs-save = Sichern
+
+## Statistics
+
+stats-today = Heute
+stats-yesterday = Gestern
+stats-menu-day = { $day }: aktiv { $enabled }, Neustarts { $restarts } (Bildschirmwechsel { $display })
+stats-column-date = Datum
+stats-column-enabled = Aktiv
+stats-column-restarts = Neustarts
+stats-column-display = Bildschirmwechsel
+stats-column-crashes = Abstürze
+stats-total = Gesamt
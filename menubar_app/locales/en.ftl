@@ -0,0 +1,170 @@
+# English, the reference catalog. Every other catalog has the same keys.
+
+## Menu
+
+menu-enable = Enable Lapsus
+menu-disable = Disable Lapsus
+menu-pause-for = Pause for
+menu-pause-minutes = { $minutes } Minutes
+menu-pause-hour = 1 Hour
+menu-start-at-login = Start at Login
+menu-preferences = Preferences…
+menu-statistics = Statistics
+menu-log-level = Log Level
+menu-log-error = Errors
+menu-log-warn = Warnings
+menu-log-info = Info
+menu-log-debug = Debug
+menu-log-trace = Trace
+menu-about = About
+menu-quit = Quit
+menu-quit-leave-running = Quit (Leave Lapsus Running)
+menu-quit-stop = Quit and Stop Lapsus
+
+## Status header and notes
+
+status-stopped = Stopped
+status-running = Running · PID { $pid }
+status-running-stats = Running · PID { $pid } · up { $uptime } · CPU { $cpu }% · { $memory }
+pause-label = Paused, resumes in { $minutes } min
+rule-disabled = Disabled while { $app } is active
+rule-enabled = Enabled while { $app } is active
+schedule-on = Scheduled: on until { $end }
+schedule-off = Scheduled: off until { $end }
+schedule-overridden = { $label } (overridden)
+power-low-power-mode = Low Power Mode
+power-battery = battery at { $percent }%
+power-disabled = Disabled: { $reason }
+power-light-profile = Light profile: { $reason }
+
+## Dialogs
+
+dialog-about-title = About
+dialog-about-version = { $app } - Version { $version }
+dialog-about-description = Menu bar app to control lapsus_rust
+dialog-error-title = Error
+dialog-choose-binary = Choose lapsus_rust
+
+## Notifications
+
+notify-error-title = Lapsus error
+notify-crash-title = Lapsus crashed
+notify-suppressed = { $body } ({ $count } similar notifications suppressed)
+state-rule-enabled = Lapsus enabled: switched to { $app }
+state-rule-disabled = Lapsus disabled: switched to { $app }
+state-schedule-enabled = Lapsus enabled by schedule
+state-schedule-disabled = Lapsus disabled by schedule
+state-power-lifted = Power policy lifted, lapsus_rust back to normal
+state-pause-ended = Pause ended, lapsus_rust resumed
+crash-restarted = lapsus_rust exited unexpectedly and was restarted
+crash-restart-failed = lapsus_rust exited unexpectedly and could not be restarted: { $error }
+crash-gave-up = lapsus_rust keeps crashing, so it was left stopped. Enable it from the menu to try again
+health-stopped = lapsus_rust has been suspended for too long
+health-cpu = lapsus_rust has used { $cpu }% CPU for { $duration }
+health-memory = lapsus_rust's memory has grown by { $growth } MB
+health-liveness = lapsus_rust has stopped touching { $file }
+health-note = Unhealthy: { $problem }
+health-restarted = { $problem }, so it was restarted
+health-restart-failed = { $problem }, and it could not be restarted: { $error }
+health-gave-up = { $problem }. It was restarted too often recently, so it was left as it is
+health-unhealthy = { $problem }
+
+## Errors
+
+error-already-running = Lapsus Control is already running and could not be reached: { $error }
+error-init = Failed to initialize app: { $error }
+error-not-found = lapsus_rust not found at: { $path }. Please ensure lapsus_rust is in the correct location.
+error-not-found-anywhere = lapsus_rust not found in any expected location. Please set the path in Preferences or bundle it with the app.
+error-hotkey = Failed to set up the toggle hotkey: { $error }
+error-start = Failed to start lapsus_rust: { $error }
+error-stop = Failed to stop lapsus_rust: { $error }
+error-pause = Failed to pause lapsus_rust: { $error }
+error-restart = Failed to restart lapsus_rust: { $error }
+error-resume = Failed to resume lapsus_rust: { $error }
+error-auto-launch = Failed to toggle auto-launch: { $error }
+error-open-preferences = Failed to open Preferences: { $error }
+error-save-preferences = Failed to save preferences: { $error }
+error-lapsus-path = Cannot use the new lapsus_rust path: { $error }
+error-daemon-login = Failed to change whether lapsus_rust starts at login: { $error }
+error-rule = Rule for { $app } failed: { $error }
+error-session = Handling { $event } failed: { $error }
+error-schedule = Scheduled change failed: { $error }
+error-power = Power policy change failed: { $error }
+error-metrics = Cannot serve metrics on port { $port }: { $error }
+
+## Hotkeys
+
+hotkey-invalid = invalid hotkey: { $reason }
+hotkey-no-modifier = hotkey "{ $hotkey }" needs Control, Option/Alt or Command/Super
+hotkey-reserved = hotkey "{ $hotkey }" is already used for { $used_for }
+shortcut-spotlight = Spotlight / input source switching
+shortcut-input-source = input source switching
+shortcut-app-switcher = the app switcher
+shortcut-window-switcher = the window switcher
+shortcut-quit = Quit
+shortcut-close-window = Close Window
+shortcut-hide = Hide
+shortcut-minimize = Minimize
+shortcut-select-all = Select All
+shortcut-copy = Copy
+shortcut-paste = Paste
+shortcut-cut = Cut
+shortcut-undo = Undo
+shortcut-save = Save
+shortcut-interrupt = Copy / interrupt
+shortcut-screenshot = screenshot
+shortcut-force-quit = Force Quit
+shortcut-lock-screen = Lock Screen
+shortcut-system-menu = the system menu
+shortcut-terminal = the terminal shortcut
+
+## Settings checks
+
+config-no-file = { $setting }: no file at { $path }
+config-no-directory = { $setting }: no directory at { $path }
+config-bad-color = { $setting }: "{ $color }" is not a color (#RRGGBB, #RGB or a name such as orange)
+config-bad-threshold = { $setting }: battery_threshold { $threshold } is over 100%
+config-problem = { $setting }: { $problem }
+schedule-invalid-day = invalid day: { $day }
+schedule-invalid-time = invalid time: { $time }
+schedule-empty = empty window: { $start }-{ $end }
+
+## Preferences window
+
+prefs-title = { $app } Preferences
+prefs-binary-section = lapsus_rust
+prefs-binary = Binary
+prefs-binary-placeholder = Search the usual locations
+prefs-browse = Choose…
+prefs-args = Launch arguments, one per line
+prefs-args-hint = Used when the app starts lapsus_rust itself, not through a LaunchAgent.
+prefs-behavior = Behavior
+prefs-start-at-login = Start the menu bar app at login
+prefs-daemon-at-login = Start lapsus_rust at login
+prefs-restore-state = Restore the last enabled state on launch
+prefs-quit = When quitting the menu bar app
+prefs-quit-stop = Stop lapsus_rust
+prefs-quit-leave-running = Leave lapsus_rust running
+prefs-display = When the cursor moves to another display
+prefs-display-restart = Restart lapsus_rust
+prefs-display-nothing = Do nothing
+prefs-hotkey = Toggle hotkey
+prefs-hotkey-placeholder = e.g. cmd+shift+l
+prefs-notifications = Notifications
+prefs-notify-errors = Errors
+prefs-notify-crashes = Crashes
+prefs-notify-changes = Automatic enable/disable
+prefs-cancel = Cancel
+prefs-save = Save
+
+## Statistics
+
+stats-today = Today
+stats-yesterday = Yesterday
+stats-menu-day = { $day }: enabled { $enabled }, restarts { $restarts } (display switch { $display })
+stats-column-date = Date
+stats-column-enabled = Enabled
+stats-column-restarts = Restarts
+stats-column-display = Display switch
+stats-column-crashes = Crashes
+stats-total = Total
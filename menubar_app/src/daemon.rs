@@ -0,0 +1,171 @@
+#[cfg(target_os = "macos")]
+use std::process::Command;
+use std::time::Duration;
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, System};
+
+use crate::i18n::t;
+
+#[cfg(target_os = "macos")]
+const LAUNCHD_LABEL: &str = "com.lapsus.rust";
+
+/// Resource usage of the lapsus_rust process, from sysinfo.
+#[derive(Debug, Clone)]
+pub struct ProcessStats {
+    pub uptime: Duration,
+    /// Percent of one core since the previous refresh
+    pub cpu_usage: f32,
+    /// Resident set size in bytes
+    pub memory: u64,
+    pub status: ProcessStatus,
+}
+
+/// A running lapsus_rust.
+#[derive(Debug, Clone)]
+pub struct DaemonInfo {
+    pub pid: u32,
+    /// Missing when launchd reports a PID that the process scan can't see
+    pub stats: Option<ProcessStats>,
+}
+
+impl DaemonInfo {
+    fn from_process(process: &Process) -> Self {
+        Self {
+            pid: process.pid().as_u32(),
+            stats: Some(ProcessStats {
+                uptime: Duration::from_secs(process.run_time()),
+                cpu_usage: process.cpu_usage(),
+                memory: process.memory(),
+                status: process.status(),
+            }),
+        }
+    }
+}
+
+pub fn is_lapsus_process(process: &Process) -> bool {
+    process.name().to_string_lossy().contains("lapsus_rust")
+}
+
+/// A zombie, or a process on its way out.
+fn has_exited(process: &Process) -> bool {
+    matches!(process.status(), ProcessStatus::Zombie | ProcessStatus::Dead)
+}
+
+/// PID of the launchd-managed lapsus_rust, if the service is loaded and running.
+#[cfg(target_os = "macos")]
+fn launchd_pid() -> Option<u32> {
+    let output = Command::new("launchctl")
+        .args(["list", LAUNCHD_LABEL])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // Output format: "PID\tStatus\tLabel" or "-\tStatus\tLabel"
+    // If first field is a number (PID), it's running
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().next()?.split_whitespace().next()?.parse().ok()
+}
+
+/// Finds lapsus_rust: the launchd job first, then any process by name (for
+/// manual starts). A process that has exited but not been reaped doesn't
+/// count, so a crash is seen as one. `system` should be long-lived so CPU
+/// usage has a baseline.
+pub fn find(system: &mut System) -> Option<DaemonInfo> {
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::new().with_cpu().with_memory(),
+    );
+
+    // Only macOS has a launchd job to ask
+    #[cfg(target_os = "macos")]
+    let launchd = launchd_pid();
+    #[cfg(not(target_os = "macos"))]
+    let launchd = None;
+
+    match launchd {
+        Some(pid) => match system.process(Pid::from_u32(pid)) {
+            Some(process) if has_exited(process) => None,
+            Some(process) => Some(DaemonInfo::from_process(process)),
+            None => Some(DaemonInfo { pid, stats: None }),
+        },
+        None => system
+            .processes()
+            .values()
+            .find(|p| is_lapsus_process(p) && !has_exited(p))
+            .map(DaemonInfo::from_process),
+    }
+}
+
+/// Header row text, e.g. "Running · PID 4512 · up 2h 14m · CPU 0.3% · 18.2 MB".
+pub fn status_text(info: Option<&DaemonInfo>) -> String {
+    let Some(info) = info else {
+        return t!("status-stopped");
+    };
+
+    match &info.stats {
+        Some(stats) => t!(
+            "status-running-stats",
+            pid = info.pid,
+            uptime = format_duration(stats.uptime),
+            cpu = format!("{:.1}", stats.cpu_usage),
+            memory = format_memory(stats.memory),
+        ),
+        None => t!("status-running", pid = info.pid),
+    }
+}
+
+/// "2d 3h", "3h 12m", "5m" or "40s".
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes) = (secs / 86_400, secs / 3_600 % 24, secs / 60 % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+fn format_memory(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_durations_by_their_two_largest_units() {
+        assert_eq!(format_duration(Duration::from_secs(40)), "40s");
+        assert_eq!(format_duration(Duration::from_secs(5 * 60 + 59)), "5m");
+        assert_eq!(format_duration(Duration::from_secs(3 * 3_600 + 12 * 60)), "3h 12m");
+        assert_eq!(format_duration(Duration::from_secs(2 * 86_400 + 3 * 3_600 + 59 * 60)), "2d 3h");
+    }
+
+    #[test]
+    fn status_shows_stats_when_the_process_was_seen() {
+        assert_eq!(status_text(None), "Stopped");
+
+        let launchd_only = DaemonInfo { pid: 4512, stats: None };
+        assert_eq!(status_text(Some(&launchd_only)), "Running · PID 4512");
+
+        let scanned = DaemonInfo {
+            pid: 4512,
+            stats: Some(ProcessStats {
+                uptime: Duration::from_secs(2 * 3_600 + 14 * 60),
+                cpu_usage: 0.3,
+                memory: 18 * 1024 * 1024 + 200 * 1024,
+                status: ProcessStatus::Run,
+            }),
+        };
+        assert_eq!(
+            status_text(Some(&scanned)),
+            "Running · PID 4512 · up 2h 14m · CPU 0.3% · 18.2 MB"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+/// AppleScript run by osascript. Text never goes into the script itself:
+/// the title and message arrive as `argv`, so no quoting can break out.
+const SCRIPT: &[&str] = &[
+    "on run argv",
+    "display dialog (item 2 of argv) with title (item 1 of argv) buttons {\"OK\"} default button \"OK\"",
+    "end run",
+];
+
+const ERROR_SCRIPT: &[&str] = &[
+    "on run argv",
+    "display dialog (item 2 of argv) with title (item 1 of argv) buttons {\"OK\"} default button \"OK\" with icon stop",
+    "end run",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogKind {
+    Info,
+    Error,
+}
+
+/// Arguments for `osascript`. The title comes first, never starting with
+/// "-", so neither it nor the message can be read as an option. NULs are
+/// dropped because process arguments can't carry them.
+pub fn osascript_args(kind: DialogKind, title: &str, message: &str) -> Vec<String> {
+    let script = match kind {
+        DialogKind::Info => SCRIPT,
+        DialogKind::Error => ERROR_SCRIPT,
+    };
+
+    let mut args = Vec::new();
+    for line in script {
+        args.push("-e".to_string());
+        args.push(line.to_string());
+    }
+    let title = title.replace('\0', "");
+    if title.is_empty() || title.starts_with('-') {
+        args.push(format!(" {}", title));
+    } else {
+        args.push(title);
+    }
+    args.push(message.replace('\0', ""));
+    args
+}
+
+/// Shows a modal dialog without waiting for it to be dismissed.
+#[cfg(target_os = "macos")]
+pub fn show(kind: DialogKind, title: &str, message: &str) {
+    let _ = std::process::Command::new("osascript")
+        .args(osascript_args(kind, title, message))
+        .spawn();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn script_part(args: &[String]) -> &[String] {
+        &args[..args.len() - 2]
+    }
+
+    proptest! {
+        #[test]
+        fn script_never_contains_user_text(title in any::<String>(), message in any::<String>()) {
+            for kind in [DialogKind::Info, DialogKind::Error] {
+                let args = osascript_args(kind, &title, &message);
+                let baseline = osascript_args(kind, "About", "");
+                prop_assert_eq!(script_part(&args), script_part(&baseline));
+            }
+        }
+
+        #[test]
+        fn text_is_passed_through_verbatim(title in "[^\0-][^\0]*", message in "[^\0]*") {
+            let args = osascript_args(DialogKind::Error, &title, &message);
+            prop_assert_eq!(&args[args.len() - 2], &title);
+            prop_assert_eq!(&args[args.len() - 1], &message);
+        }
+
+        #[test]
+        fn arguments_never_contain_nul(title in any::<String>(), message in any::<String>()) {
+            let args = osascript_args(DialogKind::Info, &title, &message);
+            prop_assert!(args.iter().all(|arg| !arg.contains('\0')));
+        }
+    }
+
+    #[test]
+    fn title_shields_message_from_option_parsing() {
+        let args = osascript_args(DialogKind::Info, "-e", "-e do shell script \"rm -rf ~\"");
+        assert_eq!(args[args.len() - 2], " -e");
+        assert_eq!(args[args.len() - 1], "-e do shell script \"rm -rf ~\"");
+    }
+}
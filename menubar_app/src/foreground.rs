@@ -0,0 +1,243 @@
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// The application currently in the foreground.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForegroundApp {
+    pub name: String,
+    pub bundle_id: Option<String>,
+}
+
+/// Platform hook that reports which application is frontmost right now.
+pub trait ForegroundSource {
+    fn frontmost(&mut self) -> Option<ForegroundApp>;
+}
+
+/// Turns a polled `ForegroundSource` into change events.
+pub struct ForegroundWatcher {
+    source: Box<dyn ForegroundSource>,
+    last: Option<ForegroundApp>,
+}
+
+impl ForegroundWatcher {
+    pub fn new(source: Box<dyn ForegroundSource>) -> Self {
+        Self { source, last: None }
+    }
+
+    /// Returns the new frontmost app if it changed since the last call.
+    pub fn poll(&mut self) -> Option<ForegroundApp> {
+        let current = self.source.frontmost()?;
+        if self.last.as_ref() == Some(&current) {
+            return None;
+        }
+        self.last = Some(current.clone());
+        Some(current)
+    }
+}
+
+/// Follows NSWorkspace's app activation notifications. The notification
+/// center calls an observer on the main thread, which the event loop
+/// runs; it hands each change over through a channel.
+#[cfg(target_os = "macos")]
+pub struct WorkspaceSource {
+    receiver: std::sync::mpsc::Receiver<ForegroundApp>,
+    current: Option<ForegroundApp>,
+}
+
+/// Where the observer sends activations; set once by `WorkspaceSource::new`.
+#[cfg(target_os = "macos")]
+static ACTIVATIONS: std::sync::OnceLock<std::sync::Mutex<std::sync::mpsc::Sender<ForegroundApp>>> =
+    std::sync::OnceLock::new();
+
+#[cfg(target_os = "macos")]
+impl WorkspaceSource {
+    pub fn new() -> Self {
+        use cocoa::base::{id, nil};
+        use cocoa::foundation::NSString;
+        use objc::declare::ClassDecl;
+        use objc::runtime::{Object, Sel};
+        use objc::{class, msg_send, sel, sel_impl};
+
+        extern "C" fn app_activated(_this: &Object, _cmd: Sel, notification: id) {
+            unsafe {
+                let info: id = msg_send![notification, userInfo];
+                let key = NSString::alloc(nil).init_str("NSWorkspaceApplicationKey");
+                let app: id = msg_send![info, objectForKey: key];
+                let _: () = msg_send![key, release];
+                if let (Some(app), Some(sender)) = (running_app(app), ACTIVATIONS.get()) {
+                    let _ = sender.lock().unwrap().send(app);
+                }
+            }
+        }
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let current = unsafe {
+            let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let frontmost: id = msg_send![workspace, frontmostApplication];
+
+            if ACTIVATIONS.set(std::sync::Mutex::new(sender)).is_ok() {
+                let mut decl = ClassDecl::new("LapsusForegroundObserver", class!(NSObject))
+                    .expect("the observer class is declared once");
+                decl.add_method(
+                    sel!(appActivated:),
+                    app_activated as extern "C" fn(&Object, Sel, id),
+                );
+                let observer: id = msg_send![decl.register(), new];
+                let center: id = msg_send![workspace, notificationCenter];
+                let name = NSString::alloc(nil).init_str("NSWorkspaceDidActivateApplicationNotification");
+                let _: () =
+                    msg_send![center, addObserver: observer selector: sel!(appActivated:) name: name object: nil];
+            } else {
+                tracing::warn!("App activations are already being watched");
+            }
+            running_app(frontmost)
+        };
+        Self { receiver, current }
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl ForegroundSource for WorkspaceSource {
+    fn frontmost(&mut self) -> Option<ForegroundApp> {
+        if let Some(app) = self.receiver.try_iter().last() {
+            self.current = Some(app);
+        }
+        self.current.clone()
+    }
+}
+
+/// Name and bundle id of an NSRunningApplication.
+#[cfg(target_os = "macos")]
+unsafe fn running_app(app: cocoa::base::id) -> Option<ForegroundApp> {
+    use objc::{msg_send, sel, sel_impl};
+
+    if app == cocoa::base::nil {
+        return None;
+    }
+    let name: cocoa::base::id = msg_send![app, localizedName];
+    let bundle_id: cocoa::base::id = msg_send![app, bundleIdentifier];
+    Some(ForegroundApp {
+        name: nsstring_to_string(name).unwrap_or_default(),
+        bundle_id: nsstring_to_string(bundle_id),
+    })
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) unsafe fn nsstring_to_string(s: cocoa::base::id) -> Option<String> {
+    use cocoa::foundation::NSString;
+
+    if s == cocoa::base::nil {
+        return None;
+    }
+    let ptr = s.UTF8String();
+    if ptr.is_null() {
+        return None;
+    }
+    Some(std::ffi::CStr::from_ptr(ptr).to_string_lossy().into_owned())
+}
+
+/// Test feed: reads the frontmost app from a file, one line
+/// `name` or `name<TAB>bundle_id`. Handy on Linux, where there is no
+/// workspace API to ask.
+pub struct FileFeedSource {
+    path: PathBuf,
+    /// The feed's modification time when it was last read
+    modified: Option<SystemTime>,
+    current: Option<ForegroundApp>,
+}
+
+impl FileFeedSource {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            modified: None,
+            current: None,
+        }
+    }
+
+    fn read(&self) -> Option<ForegroundApp> {
+        let content = std::fs::read_to_string(&self.path).ok()?;
+        let line = content.lines().rev().find(|l| !l.trim().is_empty())?;
+        let mut parts = line.splitn(2, '\t');
+        let name = parts.next()?.trim().to_string();
+        let bundle_id = parts
+            .next()
+            .map(|b| b.trim().to_string())
+            .filter(|b| !b.is_empty());
+        Some(ForegroundApp { name, bundle_id })
+    }
+}
+
+impl ForegroundSource for FileFeedSource {
+    /// Reads the file again only once it has been modified.
+    fn frontmost(&mut self) -> Option<ForegroundApp> {
+        let modified = std::fs::metadata(&self.path).and_then(|metadata| metadata.modified()).ok();
+        if modified.is_some() && modified != self.modified {
+            self.modified = modified;
+            self.current = self.read();
+        }
+        self.current.clone()
+    }
+}
+
+/// Source that never reports anything (no platform support, no feed).
+#[cfg(not(target_os = "macos"))]
+pub struct NullSource;
+
+#[cfg(not(target_os = "macos"))]
+impl ForegroundSource for NullSource {
+    fn frontmost(&mut self) -> Option<ForegroundApp> {
+        None
+    }
+}
+
+/// Picks the foreground source for this platform. `LAPSUS_FOREGROUND_FEED`
+/// overrides it with a `FileFeedSource` everywhere.
+pub fn platform_source() -> Box<dyn ForegroundSource> {
+    if let Some(path) = std::env::var_os("LAPSUS_FOREGROUND_FEED") {
+        return Box::new(FileFeedSource::new(PathBuf::from(path)));
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Box::new(WorkspaceSource::new())
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        Box::new(NullSource)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn feed_is_read_again_only_once_modified() {
+        let path = std::env::temp_dir().join(format!("lapsus-foreground-{}", std::process::id()));
+        let mut watcher = ForegroundWatcher::new(Box::new(FileFeedSource::new(path.clone())));
+        assert_eq!(watcher.poll(), None);
+
+        std::fs::write(&path, "Finder\nFigma\tcom.figma.Desktop\n").unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        let at = SystemTime::now() - Duration::from_secs(60);
+        file.set_modified(at).unwrap();
+        let figma = ForegroundApp {
+            name: "Figma".to_string(),
+            bundle_id: Some("com.figma.Desktop".to_string()),
+        };
+        assert_eq!(watcher.poll(), Some(figma));
+        assert_eq!(watcher.poll(), None);
+
+        // Same modification time: not read again
+        std::fs::write(&path, "Safari\n").unwrap();
+        file.set_modified(at).unwrap();
+        assert_eq!(watcher.poll(), None);
+
+        file.set_modified(at + Duration::from_secs(1)).unwrap();
+        assert_eq!(watcher.poll().map(|app| app.name), Some("Safari".to_string()));
+        let _ = std::fs::remove_file(&path);
+    }
+}
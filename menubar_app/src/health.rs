@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use sysinfo::ProcessStatus;
+
+use crate::daemon::{self, DaemonInfo, ProcessStats};
+use crate::i18n::t;
+
+/// How long lapsus_rust may stay stopped (e.g. by a debugger or SIGSTOP)
+/// before that counts as hung.
+const STOPPED_GRACE: Duration = Duration::from_secs(30);
+
+/// Stored in the config as `health`. Setting `cpu_percent` or
+/// `memory_growth_mb` to 0 turns that probe off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// Restart lapsus_rust when a probe fails; otherwise only show it
+    pub restart: bool,
+    /// CPU, in percent of one core, that counts as busy
+    pub cpu_percent: f32,
+    /// How long CPU must stay at or above `cpu_percent`
+    pub cpu_secs: u64,
+    /// How far resident memory may grow past its first reading
+    pub memory_growth_mb: u64,
+    /// A file lapsus_rust touches while it works
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub liveness_file: Option<PathBuf>,
+    /// How old `liveness_file` may get
+    pub liveness_secs: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            restart: true,
+            cpu_percent: 90.0,
+            cpu_secs: 60,
+            memory_growth_mb: 500,
+            liveness_file: None,
+            liveness_secs: 30,
+        }
+    }
+}
+
+/// One way of telling that lapsus_rust is running but not well. A probe
+/// lives as long as the process it watches.
+pub trait Probe: Send {
+    /// Why the process looks unhealthy, or `None`.
+    fn check(&mut self, stats: &ProcessStats, now: Instant) -> Option<String>;
+}
+
+/// Stopped for longer than `STOPPED_GRACE`. An exited process is a crash,
+/// which `daemon::find` already reports as not running.
+#[derive(Default)]
+pub struct StateProbe {
+    stopped_since: Option<Instant>,
+}
+
+impl Probe for StateProbe {
+    fn check(&mut self, stats: &ProcessStats, now: Instant) -> Option<String> {
+        if stats.status != ProcessStatus::Stop {
+            self.stopped_since = None;
+            return None;
+        }
+        let since = *self.stopped_since.get_or_insert(now);
+        (now.duration_since(since) >= STOPPED_GRACE).then(|| t!("health-stopped"))
+    }
+}
+
+/// CPU at or above a threshold for a sustained period, e.g. a busy loop.
+pub struct CpuProbe {
+    percent: f32,
+    sustain: Duration,
+    busy_since: Option<Instant>,
+}
+
+impl Probe for CpuProbe {
+    fn check(&mut self, stats: &ProcessStats, now: Instant) -> Option<String> {
+        if stats.cpu_usage < self.percent {
+            self.busy_since = None;
+            return None;
+        }
+        let since = *self.busy_since.get_or_insert(now);
+        (now.duration_since(since) >= self.sustain).then(|| {
+            t!(
+                "health-cpu",
+                cpu = format!("{:.0}", stats.cpu_usage),
+                duration = daemon::format_duration(self.sustain),
+            )
+        })
+    }
+}
+
+/// Resident memory grown by more than a limit since the first reading,
+/// e.g. a leak.
+pub struct MemoryProbe {
+    growth: u64,
+    baseline: Option<u64>,
+}
+
+impl Probe for MemoryProbe {
+    fn check(&mut self, stats: &ProcessStats, _now: Instant) -> Option<String> {
+        let baseline = *self.baseline.get_or_insert(stats.memory);
+        (stats.memory > baseline + self.growth)
+            .then(|| t!("health-memory", growth = (stats.memory - baseline) / (1024 * 1024)))
+    }
+}
+
+/// A file lapsus_rust touches that hasn't been touched recently. Its first
+/// `timeout` after starting is allowed for creating the file.
+pub struct LivenessProbe {
+    path: PathBuf,
+    timeout: Duration,
+}
+
+impl Probe for LivenessProbe {
+    fn check(&mut self, stats: &ProcessStats, _now: Instant) -> Option<String> {
+        if stats.uptime < self.timeout {
+            return None;
+        }
+        let modified = std::fs::metadata(&self.path).and_then(|metadata| metadata.modified());
+        let age = modified.map(|at| SystemTime::now().duration_since(at).unwrap_or_default());
+        match age {
+            Ok(age) if age <= self.timeout => None,
+            _ => Some(t!("health-liveness", file = self.path.display())),
+        }
+    }
+}
+
+/// The probes `config` turns on, fresh for a new process.
+pub fn probes(config: &HealthConfig) -> Vec<Box<dyn Probe>> {
+    let mut probes: Vec<Box<dyn Probe>> = vec![Box::new(StateProbe::default())];
+    if config.cpu_percent > 0.0 {
+        probes.push(Box::new(CpuProbe {
+            percent: config.cpu_percent,
+            sustain: Duration::from_secs(config.cpu_secs),
+            busy_since: None,
+        }));
+    }
+    if config.memory_growth_mb > 0 {
+        probes.push(Box::new(MemoryProbe {
+            growth: config.memory_growth_mb * 1024 * 1024,
+            baseline: None,
+        }));
+    }
+    if let Some(path) = &config.liveness_file {
+        probes.push(Box::new(LivenessProbe {
+            path: path.clone(),
+            timeout: Duration::from_secs(config.liveness_secs),
+        }));
+    }
+    probes
+}
+
+/// Runs the probes against each scan of lapsus_rust.
+pub struct HealthMonitor {
+    config: HealthConfig,
+    /// The process the probes are watching
+    pid: Option<u32>,
+    probes: Vec<Box<dyn Probe>>,
+    /// Why the current process is unhealthy, once a probe has failed
+    problem: Option<String>,
+}
+
+impl HealthMonitor {
+    pub fn new(config: HealthConfig) -> Self {
+        Self {
+            config,
+            pid: None,
+            probes: Vec::new(),
+            problem: None,
+        }
+    }
+
+    /// Whether an unhealthy lapsus_rust should be restarted.
+    pub fn restarts(&self) -> bool {
+        self.config.restart
+    }
+
+    /// Why lapsus_rust is unhealthy, while it is.
+    pub fn problem(&self) -> Option<&str> {
+        self.problem.as_deref()
+    }
+
+    /// Checks one scan; returns the problem when lapsus_rust has just
+    /// become unhealthy. A new process starts out healthy, and one that
+    /// passes every probe again is healthy again.
+    pub fn check(&mut self, daemon: Option<&DaemonInfo>, now: Instant) -> Option<String> {
+        let pid = daemon.map(|daemon| daemon.pid);
+        if pid != self.pid {
+            self.pid = pid;
+            self.probes = probes(&self.config);
+            self.problem = None;
+        }
+        // Without stats (a PID the scan can't see) there is nothing to probe
+        let stats = daemon.and_then(|daemon| daemon.stats.as_ref())?;
+
+        // Every probe runs, so each keeps track of how long it has failed
+        let problems: Vec<String> = self.probes.iter_mut().filter_map(|probe| probe.check(stats, now)).collect();
+        let was_healthy = self.problem.is_none();
+        self.problem = problems.into_iter().next();
+        if was_healthy {
+            self.problem.clone()
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn daemon(pid: u32, cpu_usage: f32, memory: u64, status: ProcessStatus) -> DaemonInfo {
+        DaemonInfo {
+            pid,
+            stats: Some(ProcessStats {
+                uptime: Duration::from_secs(600),
+                cpu_usage,
+                memory,
+                status,
+            }),
+        }
+    }
+
+    const MB: u64 = 1024 * 1024;
+
+    #[test]
+    fn sustained_cpu_is_unhealthy_once() {
+        let mut monitor = HealthMonitor::new(HealthConfig::default());
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let busy = daemon(1, 100.0, 20 * MB, ProcessStatus::Run);
+
+        assert_eq!(monitor.check(Some(&busy), at(0)), None);
+        // A dip resets the clock
+        assert_eq!(monitor.check(Some(&daemon(1, 5.0, 20 * MB, ProcessStatus::Sleep)), at(30)), None);
+        assert_eq!(monitor.check(Some(&busy), at(32)), None);
+        assert_eq!(monitor.check(Some(&busy), at(80)), None);
+        assert!(monitor.check(Some(&busy), at(92)).is_some());
+        assert!(monitor.problem().is_some());
+        // Reported once, not on every poll
+        assert_eq!(monitor.check(Some(&busy), at(94)), None);
+
+        // The replacement process starts out healthy
+        assert_eq!(monitor.check(Some(&daemon(2, 100.0, 20 * MB, ProcessStatus::Run)), at(96)), None);
+        assert_eq!(monitor.problem(), None);
+    }
+
+    #[test]
+    fn memory_growth_and_process_state() {
+        let mut monitor = HealthMonitor::new(HealthConfig::default());
+        let now = Instant::now();
+        assert_eq!(monitor.check(Some(&daemon(1, 0.0, 20 * MB, ProcessStatus::Sleep)), now), None);
+        assert_eq!(monitor.check(Some(&daemon(1, 0.0, 400 * MB, ProcessStatus::Sleep)), now), None);
+        assert!(monitor.check(Some(&daemon(1, 0.0, 600 * MB, ProcessStatus::Sleep)), now).is_some());
+
+        let mut monitor = HealthMonitor::new(HealthConfig::default());
+        let stopped = daemon(1, 0.0, 20 * MB, ProcessStatus::Stop);
+        assert_eq!(monitor.check(Some(&stopped), now), None);
+        assert!(monitor.check(Some(&stopped), now + STOPPED_GRACE).is_some());
+    }
+
+    #[test]
+    fn stale_liveness_file_is_unhealthy() {
+        let path = std::env::temp_dir().join(format!("lapsus-liveness-{}", std::process::id()));
+        let config = HealthConfig {
+            liveness_file: Some(path.clone()),
+            ..HealthConfig::default()
+        };
+        let running = daemon(1, 0.0, 20 * MB, ProcessStatus::Sleep);
+
+        // Missing
+        let mut monitor = HealthMonitor::new(config.clone());
+        assert!(monitor.check(Some(&running), Instant::now()).is_some());
+
+        // Fresh
+        let file = std::fs::File::create(&path).unwrap();
+        let mut monitor = HealthMonitor::new(config.clone());
+        assert_eq!(monitor.check(Some(&running), Instant::now()), None);
+
+        // Stale
+        file.set_modified(SystemTime::now() - Duration::from_secs(120)).unwrap();
+        assert!(monitor.check(Some(&running), Instant::now()).is_some());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,186 @@
+use global_hotkey::hotkey::{Code, HotKey, Modifiers};
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use std::fmt;
+use tray_icon::menu::accelerator::Accelerator;
+
+use crate::i18n::{self, t};
+
+/// Shortcuts the OS or nearly every app already uses, with the catalog key
+/// describing each. "super" is Command on macOS and the Windows/Super key
+/// elsewhere.
+const RESERVED: &[(Modifiers, Code, &str)] = &[
+    (Modifiers::SUPER, Code::Space, "shortcut-spotlight"),
+    (Modifiers::CONTROL, Code::Space, "shortcut-input-source"),
+    (Modifiers::SUPER, Code::Tab, "shortcut-app-switcher"),
+    (Modifiers::ALT, Code::Tab, "shortcut-window-switcher"),
+    (Modifiers::SUPER, Code::KeyQ, "shortcut-quit"),
+    (Modifiers::SUPER, Code::KeyW, "shortcut-close-window"),
+    (Modifiers::SUPER, Code::KeyH, "shortcut-hide"),
+    (Modifiers::SUPER, Code::KeyM, "shortcut-minimize"),
+    (Modifiers::SUPER, Code::KeyA, "shortcut-select-all"),
+    (Modifiers::SUPER, Code::KeyC, "shortcut-copy"),
+    (Modifiers::SUPER, Code::KeyV, "shortcut-paste"),
+    (Modifiers::SUPER, Code::KeyX, "shortcut-cut"),
+    (Modifiers::SUPER, Code::KeyZ, "shortcut-undo"),
+    (Modifiers::SUPER, Code::KeyS, "shortcut-save"),
+    (Modifiers::CONTROL, Code::KeyC, "shortcut-interrupt"),
+    (Modifiers::CONTROL, Code::KeyV, "shortcut-paste"),
+    (Modifiers::CONTROL, Code::KeyX, "shortcut-cut"),
+    (Modifiers::CONTROL, Code::KeyZ, "shortcut-undo"),
+    (Modifiers::SUPER.union(Modifiers::SHIFT), Code::Digit3, "shortcut-screenshot"),
+    (Modifiers::SUPER.union(Modifiers::SHIFT), Code::Digit4, "shortcut-screenshot"),
+    (Modifiers::SUPER.union(Modifiers::SHIFT), Code::Digit5, "shortcut-screenshot"),
+    (Modifiers::SUPER.union(Modifiers::ALT), Code::Escape, "shortcut-force-quit"),
+    (Modifiers::CONTROL.union(Modifiers::SUPER), Code::KeyQ, "shortcut-lock-screen"),
+    (Modifiers::CONTROL.union(Modifiers::ALT), Code::Delete, "shortcut-system-menu"),
+    (Modifiers::CONTROL.union(Modifiers::ALT), Code::KeyT, "shortcut-terminal"),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum HotkeyError {
+    /// Not a key combination at all, e.g. "cmd+shift+"
+    Invalid(String),
+    /// Needs Control, Option/Alt or Command/Super, or it would eat typing
+    NoModifier(String),
+    /// Clashes with a well-known system or editing shortcut; the catalog
+    /// key of what it does
+    Reserved(String, &'static str),
+}
+
+impl fmt::Display for HotkeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HotkeyError::Invalid(reason) => write!(f, "{}", t!("hotkey-invalid", reason = reason)),
+            HotkeyError::NoModifier(hotkey) => write!(f, "{}", t!("hotkey-no-modifier", hotkey = hotkey)),
+            HotkeyError::Reserved(hotkey, used_for) => {
+                let used_for = i18n::message(used_for, &[]);
+                write!(f, "{}", t!("hotkey-reserved", hotkey = hotkey, used_for = used_for))
+            }
+        }
+    }
+}
+
+impl std::error::Error for HotkeyError {}
+
+/// Parses and validates an accelerator string such as "cmd+shift+l" or
+/// "Ctrl + Alt + L". Modifier and key names are case-insensitive.
+pub fn parse(accelerator: &str) -> Result<HotKey, HotkeyError> {
+    let hotkey: HotKey = accelerator
+        .parse()
+        .map_err(|e: global_hotkey::hotkey::HotKeyParseError| HotkeyError::Invalid(e.to_string()))?;
+
+    if !hotkey.mods.intersects(Modifiers::CONTROL | Modifiers::ALT | Modifiers::SUPER) {
+        return Err(HotkeyError::NoModifier(accelerator.to_string()));
+    }
+
+    if let Some((_, _, used_for)) = RESERVED
+        .iter()
+        .find(|(mods, key, _)| hotkey.mods == *mods && hotkey.key == *key)
+    {
+        return Err(HotkeyError::Reserved(accelerator.to_string(), used_for));
+    }
+
+    Ok(hotkey)
+}
+
+/// The same shortcut as shown next to a menu item.
+pub fn menu_accelerator(hotkey: &HotKey) -> Accelerator {
+    Accelerator::new(Some(hotkey.mods), hotkey.key)
+}
+
+/// The registered toggle shortcut. Unregistered when dropped.
+pub struct ToggleHotkey {
+    manager: GlobalHotKeyManager,
+    hotkey: HotKey,
+}
+
+impl ToggleHotkey {
+    pub fn register(accelerator: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let hotkey = parse(accelerator)?;
+        let manager = GlobalHotKeyManager::new()?;
+        manager
+            .register(hotkey)
+            .map_err(|e| format!("could not register hotkey \"{}\": {}", accelerator, e))?;
+        Ok(Self { manager, hotkey })
+    }
+
+    pub fn hotkey(&self) -> &HotKey {
+        &self.hotkey
+    }
+
+    /// Drains pending hotkey events; true if ours was pressed since last call.
+    pub fn pressed(&self) -> bool {
+        let mut pressed = false;
+        while let Ok(event) = GlobalHotKeyEvent::receiver().try_recv() {
+            if event.id() == self.hotkey.id() && event.state() == HotKeyState::Pressed {
+                pressed = true;
+            }
+        }
+        pressed
+    }
+}
+
+impl Drop for ToggleHotkey {
+    fn drop(&mut self) {
+        let _ = self.manager.unregister(self.hotkey);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_modifiers_in_any_case_and_spacing() {
+        let hotkey = parse("Ctrl + Alt + L").unwrap();
+        assert_eq!(hotkey.mods, Modifiers::CONTROL | Modifiers::ALT);
+        assert_eq!(hotkey.key, Code::KeyL);
+        assert_eq!(hotkey, parse("control+option+KeyL").unwrap());
+        assert_eq!(parse("super+shift+l").unwrap(), parse("CMD+SHIFT+L").unwrap());
+    }
+
+    #[test]
+    fn rejects_malformed_accelerators() {
+        assert!(matches!(parse(""), Err(HotkeyError::Invalid(_))));
+        assert!(matches!(parse("cmd+shift+"), Err(HotkeyError::Invalid(_))));
+        assert!(matches!(parse("cmd+l+k"), Err(HotkeyError::Invalid(_))));
+        assert!(matches!(parse("cmd+nosuchkey"), Err(HotkeyError::Invalid(_))));
+    }
+
+    #[test]
+    fn requires_a_real_modifier() {
+        assert!(matches!(parse("l"), Err(HotkeyError::NoModifier(_))));
+        assert!(matches!(parse("shift+l"), Err(HotkeyError::NoModifier(_))));
+        assert!(parse("alt+l").is_ok());
+    }
+
+    #[test]
+    fn rejects_system_shortcuts() {
+        for accelerator in ["cmd+space", "Super+Tab", "cmd+q", "cmd+shift+3", "ctrl+c", "cmd+option+escape"] {
+            assert!(
+                matches!(parse(accelerator), Err(HotkeyError::Reserved(..))),
+                "{} should be reserved",
+                accelerator
+            );
+        }
+        // Adding a modifier makes it a different shortcut
+        assert!(parse("cmd+shift+space").is_ok());
+        assert!(parse("ctrl+shift+c").is_ok());
+    }
+
+    #[test]
+    fn reserved_shortcuts_are_in_the_catalog() {
+        for (_, _, key) in RESERVED {
+            assert_ne!(i18n::message(key, &[]), *key, "{} is missing from the catalog", key);
+        }
+    }
+
+    #[test]
+    fn menu_accelerator_matches_hotkey() {
+        let hotkey = parse("cmd+shift+l").unwrap();
+        assert_eq!(
+            menu_accelerator(&hotkey),
+            Accelerator::new(Some(Modifiers::SUPER | Modifiers::SHIFT), Code::KeyL)
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Shipped catalogs, English first. Each is a Fluent file restricted to
+/// simple messages: `key = text`, with `{ $name }` placeables and `#`
+/// comments. No selectors, terms or attributes.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+];
+
+static ENGLISH: OnceLock<Catalog> = OnceLock::new();
+static ACTIVE: OnceLock<Catalog> = OnceLock::new();
+
+/// Looks up a message in the active catalog, e.g. `t!("menu-quit")` or
+/// `t!("error-start", error = e)`.
+macro_rules! t {
+    ($key:literal) => {
+        $crate::i18n::message($key, &[])
+    };
+    ($key:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::message($key, &[$((stringify!($name), $value.to_string())),+])
+    };
+}
+pub(crate) use t;
+
+pub struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    pub fn parse(source: &str) -> Self {
+        let messages = source
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, text)| (key.trim().to_string(), text.trim().to_string()))
+            .collect();
+        Self { messages }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.messages.get(key).map(String::as_str)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.messages.keys().map(String::as_str)
+    }
+}
+
+/// Picks the catalog for `requested` (from `--lang`), else the system
+/// locale. Call once, before anything is shown; later calls are ignored.
+pub fn init(requested: Option<&str>) {
+    let tag = requested.map(str::to_string).or_else(sys_locale::get_locale);
+    let language = tag.as_deref().map(language).unwrap_or_default();
+
+    let source = CATALOGS.iter().find(|(code, _)| *code == language).map(|(_, source)| source);
+    if source.is_none() && requested.is_some() {
+        let available: Vec<&str> = CATALOGS.iter().map(|(code, _)| *code).collect();
+        tracing::warn!(
+            "No translation for {:?}, using English. Available: {}",
+            language,
+            available.join(", ")
+        );
+    }
+    let _ = ACTIVE.set(Catalog::parse(source.unwrap_or(&CATALOGS[0].1)));
+}
+
+/// `--lang de` or `--lang=de` from the command line.
+pub fn lang_arg(mut args: impl Iterator<Item = String>) -> Option<String> {
+    while let Some(arg) = args.next() {
+        if arg == "--lang" {
+            return args.next();
+        }
+        if let Some(lang) = arg.strip_prefix("--lang=") {
+            return Some(lang.to_string());
+        }
+    }
+    None
+}
+
+/// Primary language subtag: "de" for "de_DE.UTF-8", "pt" for "pt-BR".
+fn language(tag: &str) -> String {
+    tag.split(['-', '_', '.', '@']).next().unwrap_or_default().to_lowercase()
+}
+
+/// The message for `key` with its placeables filled in. Falls back to
+/// English, then to the key itself, so a gap never hides text entirely.
+pub fn message(key: &str, args: &[(&str, String)]) -> String {
+    let english = ENGLISH.get_or_init(|| Catalog::parse(CATALOGS[0].1));
+    let pattern = ACTIVE
+        .get()
+        .and_then(|catalog| catalog.get(key))
+        .or_else(|| english.get(key))
+        .unwrap_or(key);
+    format(pattern, args)
+}
+
+/// All messages whose key starts with `prefix`, for the preferences page.
+pub fn messages_with_prefix(prefix: &str) -> HashMap<String, String> {
+    let english = ENGLISH.get_or_init(|| Catalog::parse(CATALOGS[0].1));
+    english
+        .keys()
+        .filter(|key| key.starts_with(prefix))
+        .map(|key| (key.to_string(), message(key, &[])))
+        .collect()
+}
+
+fn format(pattern: &str, args: &[(&str, String)]) -> String {
+    let mut text = String::new();
+    let mut rest = pattern;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}').map(|close| open + close) else {
+            break;
+        };
+        text.push_str(&rest[..open]);
+        let placeable = rest[open + 1..close].trim();
+        match placeable
+            .strip_prefix('$')
+            .and_then(|name| args.iter().find(|(arg, _)| *arg == name))
+        {
+            Some((_, value)) => text.push_str(value),
+            None => text.push_str(&rest[open..=close]),
+        }
+        rest = &rest[close + 1..];
+    }
+    text.push_str(rest);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn placeables(pattern: &str) -> BTreeSet<String> {
+        pattern
+            .split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}'))
+            .map(|(placeable, _)| placeable.trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn every_catalog_has_every_key() {
+        let english = Catalog::parse(CATALOGS[0].1);
+        for (code, source) in &CATALOGS[1..] {
+            let catalog = Catalog::parse(source);
+            let missing: BTreeSet<_> = english.keys().filter(|key| catalog.get(key).is_none()).collect();
+            let extra: BTreeSet<_> = catalog.keys().filter(|key| english.get(key).is_none()).collect();
+            assert!(missing.is_empty(), "{} is missing {:?}", code, missing);
+            assert!(extra.is_empty(), "{} has keys English doesn't: {:?}", code, extra);
+
+            for key in english.keys() {
+                assert_eq!(
+                    placeables(english.get(key).unwrap()),
+                    placeables(catalog.get(key).unwrap()),
+                    "{} uses different placeables for {}",
+                    code,
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_key_in_the_source_is_in_english() {
+        let english = Catalog::parse(CATALOGS[0].1);
+        let src = concat!(env!("CARGO_MANIFEST_DIR"), "/src");
+        for entry in std::fs::read_dir(src).unwrap() {
+            let path = entry.unwrap().path();
+            let source = std::fs::read_to_string(&path).unwrap();
+            let markers = ["t!(\"", "data-i18n=\"", "data-i18n-placeholder=\""];
+            for marker in markers {
+                for (at, _) in source.match_indices(marker) {
+                    // Skip `format!(` and the like
+                    if source[..at].ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+                        continue;
+                    }
+                    let key = source[at + marker.len()..].split('"').next().unwrap();
+                    assert!(english.get(key).is_some(), "{} uses unknown key {}", path.display(), key);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fills_in_placeables() {
+        let args = [("minutes", "5".to_string())];
+        assert_eq!(format("{ $minutes } Minutes", &args), "5 Minutes");
+        assert_eq!(format("in {$minutes} min", &args), "in 5 min");
+        assert_eq!(format("{ $unknown } and { brace", &args), "{ $unknown } and { brace");
+    }
+
+    #[test]
+    fn parses_catalogs() {
+        let catalog = Catalog::parse("# comment\n\nmenu-quit = Quit\nstatus = a = b\n");
+        assert_eq!(catalog.get("menu-quit"), Some("Quit"));
+        assert_eq!(catalog.get("status"), Some("a = b"));
+        assert_eq!(catalog.keys().count(), 2);
+    }
+
+    #[test]
+    fn reduces_locales_to_a_language() {
+        assert_eq!(language("de_DE.UTF-8"), "de");
+        assert_eq!(language("pt-BR"), "pt");
+        assert_eq!(language("EN"), "en");
+    }
+
+    #[test]
+    fn reads_lang_from_arguments() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().into_iter();
+        assert_eq!(lang_arg(args(&["app", "--lang", "de"])), Some("de".to_string()));
+        assert_eq!(lang_arg(args(&["app", "--lang=de"])), Some("de".to_string()));
+        assert_eq!(lang_arg(args(&["app"])), None);
+    }
+}
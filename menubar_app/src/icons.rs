@@ -0,0 +1,798 @@
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+#[cfg(not(target_os = "macos"))]
+use std::io::{BufRead, BufReader};
+#[cfg(not(target_os = "macos"))]
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+#[cfg(not(target_os = "macos"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(not(target_os = "macos"))]
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tray_icon::Icon;
+
+use crate::svg;
+
+/// Compiled in so a missing or broken Resources folder can't stop startup.
+const BUILTIN_ENABLED: &[u8] = include_bytes!("../icons/cursor_enabled.svg");
+const BUILTIN_DISABLED: &[u8] = include_bytes!("../icons/cursor_disabled.svg");
+
+/// Icon height in points (macOS) or logical pixels, before scaling.
+#[cfg(target_os = "macos")]
+const ICON_POINTS: f64 = 18.0;
+#[cfg(target_os = "windows")]
+const ICON_POINTS: f64 = 16.0;
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const ICON_POINTS: f64 = 22.0;
+
+/// Frames generated for the built-in start/stop animation.
+const BUILTIN_FRAMES: usize = 5;
+
+/// How long a start or stop may take before the icon shows an error.
+const TRANSITION_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconState {
+    Enabled,
+    Disabled,
+    Starting,
+    Stopping,
+    Error,
+    Paused,
+    /// Running but failing a health check
+    Degraded,
+}
+
+/// Per-state colors as "#RRGGBB", "#RGB" or a color name, stored in the config as `icon_tints`.
+/// A tinted state is drawn in color instead of as a macOS template.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IconTints {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<String>,
+    /// Also used while stopping
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starting: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paused: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub degraded: Option<String>,
+}
+
+impl IconTints {
+    pub fn is_empty(&self) -> bool {
+        self.enabled.is_none()
+            && self.disabled.is_none()
+            && self.starting.is_none()
+            && self.error.is_none()
+            && self.paused.is_none()
+            && self.degraded.is_none()
+    }
+
+    fn color(&self, state: IconState) -> Option<[u8; 3]> {
+        let color = match state {
+            IconState::Enabled => &self.enabled,
+            IconState::Disabled => &self.disabled,
+            IconState::Starting | IconState::Stopping => &self.starting,
+            IconState::Error => &self.error,
+            IconState::Paused => &self.paused,
+            IconState::Degraded => &self.degraded,
+        };
+        svg::parse_color(color.as_deref()?)
+    }
+
+    /// Colors `svg::parse_color` doesn't understand.
+    pub fn invalid(&self) -> Vec<&str> {
+        [&self.enabled, &self.disabled, &self.starting, &self.error, &self.paused, &self.degraded]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .filter(|color| svg::parse_color(color).is_none())
+            .collect()
+    }
+
+    fn check(&self) {
+        for color in self.invalid() {
+            tracing::warn!("Ignoring icon tint {:?}: expected \"#RRGGBB\", \"#RGB\" or a color name", color);
+        }
+    }
+}
+
+/// Optional `theme.json` in a theme directory.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct ThemeManifest {
+    /// Let macOS recolor the icons for light and dark menu bars
+    template: bool,
+    /// Delay between animation frames
+    frame_ms: u64,
+}
+
+impl Default for ThemeManifest {
+    fn default() -> Self {
+        Self {
+            template: true,
+            frame_ms: 150,
+        }
+    }
+}
+
+/// An icon image before it is sized: vector sources are rendered at the
+/// exact pixel height, bitmaps are resampled.
+#[derive(Clone)]
+enum Source {
+    Svg(Rc<[u8]>),
+    Bitmap(RgbaImage),
+}
+
+impl Source {
+    fn load(dir: &Path, name: &str) -> Option<Self> {
+        let svg_path = dir.join(format!("{}.svg", name));
+        if svg_path.exists() {
+            match std::fs::read(&svg_path) {
+                Ok(data) => return Some(Source::Svg(data.into())),
+                Err(e) => tracing::warn!("Failed to read icon {:?}: {}", svg_path, e),
+            }
+        }
+
+        let png_path = dir.join(format!("{}.png", name));
+        if !png_path.exists() {
+            return None;
+        }
+        match image::open(&png_path) {
+            Ok(image) => Some(Source::Bitmap(image.to_rgba8())),
+            Err(e) => {
+                tracing::warn!("Failed to load icon {:?}: {}", png_path, e);
+                None
+            }
+        }
+    }
+
+    fn rasterize(&self, height: u32) -> Option<RgbaImage> {
+        match self {
+            Source::Svg(data) => svg::render(data, height)
+                .map_err(|e| tracing::warn!("Failed to render SVG icon: {}", e))
+                .ok(),
+            Source::Bitmap(image) if image.height() == height => Some(image.clone()),
+            Source::Bitmap(image) => {
+                let width = (image.width() as f64 * height as f64 / image.height() as f64).round() as u32;
+                Some(imageops::resize(image, width.max(1), height, FilterType::Lanczos3))
+            }
+        }
+    }
+}
+
+/// The sources for one appearance (light or dark menu bar). Missing states
+/// are derived from `enabled` and `disabled` when rendering.
+#[derive(Clone)]
+struct Sources {
+    enabled: Source,
+    disabled: Source,
+    error: Option<Source>,
+    paused: Option<Source>,
+    degraded: Option<Source>,
+    starting: Vec<Source>,
+}
+
+impl Sources {
+    fn load(dir: &Path, suffix: &str, fallback: &Sources) -> Self {
+        let file = |name: &str| Source::load(dir, &format!("{}{}", name, suffix));
+
+        let numbered: Vec<Source> = (1..).map_while(|i| file(&format!("starting-{}", i))).collect();
+        let starting = if numbered.is_empty() {
+            file("starting").into_iter().collect()
+        } else {
+            numbered
+        };
+
+        Self {
+            enabled: file("enabled").unwrap_or_else(|| fallback.enabled.clone()),
+            disabled: file("disabled").unwrap_or_else(|| fallback.disabled.clone()),
+            error: file("error"),
+            paused: file("paused"),
+            degraded: file("degraded"),
+            starting,
+        }
+    }
+
+    fn render(&self, height: u32, fallback: &Variants) -> Variants {
+        let enabled = self.enabled.rasterize(height).unwrap_or_else(|| fallback.enabled.clone());
+        let disabled = self.disabled.rasterize(height).unwrap_or_else(|| fallback.disabled.clone());
+        let mut variants = Variants::derived(enabled, disabled);
+
+        if let Some(error) = self.error.as_ref().and_then(|s| s.rasterize(height)) {
+            variants.error = error;
+        }
+        if let Some(paused) = self.paused.as_ref().and_then(|s| s.rasterize(height)) {
+            variants.paused = paused;
+        }
+        if let Some(degraded) = self.degraded.as_ref().and_then(|s| s.rasterize(height)) {
+            variants.degraded = degraded;
+        }
+        let starting: Vec<RgbaImage> = self.starting.iter().filter_map(|s| s.rasterize(height)).collect();
+        if !starting.is_empty() {
+            variants.starting = starting;
+        }
+        variants
+    }
+}
+
+/// Where the tray icons come from, loaded once and rendered per scale.
+///
+/// A theme is a directory with any of `enabled`, `disabled`, `error`,
+/// `paused`, `degraded` and `starting` (or numbered animation frames `starting-1`,
+/// `starting-2`, ...), each as `.svg` or `.png`. Files ending in `-dark` are
+/// used on dark menu bars when the theme isn't a template.
+pub struct IconTheme {
+    light: Sources,
+    dark: Option<Sources>,
+    /// The built-in icons are black; invert them where the OS won't
+    invert_for_dark: bool,
+    template: bool,
+    frame_interval: Duration,
+    tints: IconTints,
+}
+
+impl IconTheme {
+    pub fn builtin(tints: IconTints) -> Self {
+        let manifest = ThemeManifest::default();
+        tints.check();
+        Self {
+            light: Sources {
+                enabled: Source::Svg(BUILTIN_ENABLED.into()),
+                disabled: Source::Svg(BUILTIN_DISABLED.into()),
+                error: None,
+                paused: None,
+                degraded: None,
+                starting: Vec::new(),
+            },
+            dark: None,
+            invert_for_dark: true,
+            template: manifest.template,
+            frame_interval: Duration::from_millis(manifest.frame_ms),
+            tints,
+        }
+    }
+
+    /// Loads `theme_dir`, falling back to the built-in icons for anything
+    /// missing or unreadable.
+    pub fn load(theme_dir: Option<&Path>, tints: IconTints) -> Self {
+        let builtin = Self::builtin(tints);
+        let Some(dir) = theme_dir else {
+            return builtin;
+        };
+        if !dir.is_dir() {
+            tracing::warn!("Icon theme {:?} not found, using built-in icons", dir);
+            return builtin;
+        }
+
+        let manifest = match std::fs::read_to_string(dir.join("theme.json")) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring invalid {:?}: {}", dir.join("theme.json"), e);
+                ThemeManifest::default()
+            }),
+            Err(_) => ThemeManifest::default(),
+        };
+
+        let light = Sources::load(dir, "", &builtin.light);
+        let has_dark = ["enabled", "disabled"].iter().any(|name| {
+            ["svg", "png"]
+                .iter()
+                .any(|ext| dir.join(format!("{}-dark.{}", name, ext)).exists())
+        });
+        let dark = has_dark.then(|| Sources::load(dir, "-dark", &light));
+
+        Self {
+            light,
+            dark,
+            invert_for_dark: false,
+            template: manifest.template,
+            frame_interval: Duration::from_millis(manifest.frame_ms.max(30)),
+            tints: builtin.tints,
+        }
+    }
+
+    /// True if the icons depend on the menu bar appearance, so it's worth
+    /// watching for dark mode changes.
+    pub fn follows_appearance(&self) -> bool {
+        (self.dark.is_some() || self.invert_for_dark) && !(cfg!(target_os = "macos") && self.template)
+    }
+}
+
+/// Pixel height of the tray icon on a display with this scale factor.
+pub fn pixel_height(scale: f64) -> u32 {
+    (ICON_POINTS * scale.clamp(1.0, 4.0)).round() as u32
+}
+
+/// One image per state for a single menu bar appearance.
+#[derive(Clone)]
+struct Variants {
+    enabled: RgbaImage,
+    disabled: RgbaImage,
+    error: RgbaImage,
+    paused: RgbaImage,
+    degraded: RgbaImage,
+    /// Played forwards while starting and backwards while stopping
+    starting: Vec<RgbaImage>,
+}
+
+impl Variants {
+    /// Derive the states a theme doesn't draw from its enabled/disabled pair.
+    fn derived(enabled: RgbaImage, disabled: RgbaImage) -> Self {
+        let starting = (0..BUILTIN_FRAMES)
+            .map(|i| crossfade(&disabled, &enabled, (i + 1) as f32 / (BUILTIN_FRAMES + 1) as f32))
+            .collect();
+        Self {
+            error: with_badge(&disabled),
+            paused: faded(&disabled, 0.45),
+            degraded: with_badge(&enabled),
+            starting,
+            enabled,
+            disabled,
+        }
+    }
+
+    fn map(&self, f: impl Fn(&RgbaImage) -> RgbaImage) -> Self {
+        Self {
+            enabled: f(&self.enabled),
+            disabled: f(&self.disabled),
+            error: f(&self.error),
+            paused: f(&self.paused),
+            degraded: f(&self.degraded),
+            starting: self.starting.iter().map(&f).collect(),
+        }
+    }
+
+    fn tint(&mut self, tints: &IconTints) {
+        let apply = |image: &mut RgbaImage, state| {
+            if let Some(color) = tints.color(state) {
+                *image = svg::tinted(image, color);
+            }
+        };
+        apply(&mut self.enabled, IconState::Enabled);
+        apply(&mut self.disabled, IconState::Disabled);
+        apply(&mut self.error, IconState::Error);
+        apply(&mut self.paused, IconState::Paused);
+        apply(&mut self.degraded, IconState::Degraded);
+        for frame in &mut self.starting {
+            apply(frame, IconState::Starting);
+        }
+    }
+}
+
+/// Tray icons for every state, rendered for one pixel height.
+pub struct IconSet {
+    light: Variants,
+    dark: Option<Variants>,
+    height: u32,
+    template: bool,
+    follows_appearance: bool,
+    frame_interval: Duration,
+    tints: IconTints,
+}
+
+impl IconSet {
+    pub fn render(theme: &IconTheme, height: u32) -> Self {
+        // A blank image is the last resort if even the built-in SVG fails
+        let blank = RgbaImage::new(height, height);
+        let fallback = Variants::derived(blank.clone(), blank);
+
+        let mut light = theme.light.render(height, &fallback);
+        let mut dark = match &theme.dark {
+            Some(sources) => Some(sources.render(height, &light)),
+            None if theme.invert_for_dark => Some(light.map(inverted)),
+            None => None,
+        };
+        light.tint(&theme.tints);
+        if let Some(dark) = &mut dark {
+            dark.tint(&theme.tints);
+        }
+
+        Self {
+            light,
+            dark,
+            height,
+            template: theme.template,
+            follows_appearance: theme.follows_appearance(),
+            frame_interval: theme.frame_interval,
+            tints: theme.tints.clone(),
+        }
+    }
+
+    /// Whether macOS should draw `state` as a template; tinted states keep
+    /// their color.
+    pub fn is_template(&self, state: IconState) -> bool {
+        self.template && self.tints.color(state).is_none()
+    }
+
+    pub fn frame_interval(&self) -> Duration {
+        self.frame_interval
+    }
+
+    pub fn frame_count(&self, state: IconState) -> usize {
+        match state {
+            IconState::Starting | IconState::Stopping => self.light.starting.len(),
+            _ => 1,
+        }
+    }
+
+    fn image(&self, state: IconState, frame: usize, dark: bool) -> &RgbaImage {
+        let variants = match &self.dark {
+            Some(dark_variants) if dark && self.follows_appearance => dark_variants,
+            _ => &self.light,
+        };
+        let frames = &variants.starting;
+        match state {
+            IconState::Enabled => &variants.enabled,
+            IconState::Disabled => &variants.disabled,
+            IconState::Error => &variants.error,
+            IconState::Paused => &variants.paused,
+            IconState::Degraded => &variants.degraded,
+            IconState::Starting => &frames[frame % frames.len()],
+            IconState::Stopping => &frames[frames.len() - 1 - frame % frames.len()],
+        }
+    }
+
+    pub fn icon(&self, state: IconState, frame: usize, dark: bool) -> Icon {
+        let image = self.image(state, frame, dark);
+        let (width, height) = image.dimensions();
+        Icon::from_rgba(image.as_raw().clone(), width, height).expect("RGBA buffer matches its dimensions")
+    }
+}
+
+/// Rendered icon sets by pixel height, so moving between displays doesn't
+/// re-render.
+pub struct IconCache {
+    theme: IconTheme,
+    sets: HashMap<u32, Rc<IconSet>>,
+}
+
+impl IconCache {
+    pub fn new(theme: IconTheme) -> Self {
+        Self {
+            theme,
+            sets: HashMap::new(),
+        }
+    }
+
+    pub fn follows_appearance(&self) -> bool {
+        self.theme.follows_appearance()
+    }
+
+    pub fn get(&mut self, scale: f64) -> Rc<IconSet> {
+        let height = pixel_height(scale);
+        self.sets
+            .entry(height)
+            .or_insert_with(|| Rc::new(IconSet::render(&self.theme, height)))
+            .clone()
+    }
+}
+
+fn crossfade(from: &RgbaImage, to: &RgbaImage, t: f32) -> RgbaImage {
+    let (width, height) = from.dimensions();
+    let to = if to.dimensions() == (width, height) {
+        to.clone()
+    } else {
+        imageops::resize(to, width, height, FilterType::Triangle)
+    };
+    RgbaImage::from_fn(width, height, |x, y| {
+        let (a, b) = (from.get_pixel(x, y), to.get_pixel(x, y));
+        Rgba(std::array::from_fn(|c| {
+            (a[c] as f32 + (b[c] as f32 - a[c] as f32) * t).round() as u8
+        }))
+    })
+}
+
+fn faded(image: &RgbaImage, opacity: f32) -> RgbaImage {
+    let mut image = image.clone();
+    for pixel in image.pixels_mut() {
+        pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+    }
+    image
+}
+
+fn inverted(image: &RgbaImage) -> RgbaImage {
+    let mut image = image.clone();
+    for pixel in image.pixels_mut() {
+        for c in 0..3 {
+            pixel[c] = 255 - pixel[c];
+        }
+    }
+    image
+}
+
+/// Draws a solid dot in the bottom-right corner; it survives template
+/// rendering, unlike a color change.
+fn with_badge(image: &RgbaImage) -> RgbaImage {
+    let mut image = image.clone();
+    let (width, height) = image.dimensions();
+    let radius = (width.min(height) as f32 / 5.0).max(2.0);
+    let (cx, cy) = (width as f32 - radius - 0.5, height as f32 - radius - 0.5);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+        if dx * dx + dy * dy <= radius * radius {
+            *pixel = Rgba([0, 0, 0, 255]);
+        }
+    }
+    image
+}
+
+/// Whether the menu bar or panel is dark, kept current without starting a
+/// process on every poll.
+pub struct Appearance {
+    #[cfg(not(target_os = "macos"))]
+    dark: Arc<AtomicBool>,
+}
+
+impl Appearance {
+    /// Reads the setting from the process's own defaults, which macOS keeps
+    /// in sync with the system.
+    #[cfg(target_os = "macos")]
+    pub fn watch() -> Self {
+        Self {}
+    }
+
+    /// Reads the GNOME color scheme once, then follows `gsettings monitor`
+    /// from a background thread.
+    #[cfg(not(target_os = "macos"))]
+    pub fn watch() -> Self {
+        let scheme = Command::new("gsettings")
+            .args(["get", "org.gnome.desktop.interface", "color-scheme"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default();
+        let dark = Arc::new(AtomicBool::new(is_dark_scheme(&scheme)));
+
+        let monitor = Command::new("gsettings")
+            .args(["monitor", "org.gnome.desktop.interface", "color-scheme"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        match monitor {
+            Ok(mut child) => {
+                let stdout = child.stdout.take().expect("stdout is piped");
+                let dark = dark.clone();
+                let spawned = std::thread::Builder::new().name("appearance".to_string()).spawn(move || {
+                    // Each change is one line, e.g. "color-scheme: 'prefer-dark'"
+                    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                        dark.store(is_dark_scheme(&line), Ordering::Relaxed);
+                    }
+                    let _ = child.wait();
+                });
+                if let Err(e) = spawned {
+                    tracing::warn!("Failed to watch the color scheme: {}", e);
+                }
+            }
+            Err(e) => tracing::debug!("Not following dark mode changes: gsettings monitor: {}", e),
+        }
+        Self { dark }
+    }
+
+    #[cfg(target_os = "macos")]
+    pub fn is_dark(&self) -> bool {
+        use cocoa::base::id;
+        use cocoa::foundation::NSString;
+        use objc::{class, msg_send, sel, sel_impl};
+
+        unsafe {
+            let defaults: id = msg_send![class!(NSUserDefaults), standardUserDefaults];
+            let key = NSString::alloc(cocoa::base::nil).init_str("AppleInterfaceStyle");
+            let style: id = msg_send![defaults, stringForKey: key];
+            let _: () = msg_send![key, release];
+            crate::foreground::nsstring_to_string(style).as_deref() == Some("Dark")
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    pub fn is_dark(&self) -> bool {
+        self.dark.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn is_dark_scheme(scheme: &str) -> bool {
+    scheme.contains("prefer-dark")
+}
+
+struct Transition {
+    to_running: bool,
+    deadline: Instant,
+}
+
+/// Decides which icon and animation frame the tray shows.
+pub struct IconDisplay {
+    transition: Option<Transition>,
+    /// Set after a failed start/stop: the running state the failure left
+    /// behind. The error icon stays until that changes or a new attempt.
+    error_while: Option<bool>,
+    /// Running lapsus_rust is failing a health check
+    degraded: bool,
+    frame: usize,
+    frame_at: Instant,
+    dark: bool,
+    shown: Option<(IconState, usize, bool, u32)>,
+}
+
+impl IconDisplay {
+    pub fn new(dark: bool) -> Self {
+        Self {
+            transition: None,
+            error_while: None,
+            degraded: false,
+            frame: 0,
+            frame_at: Instant::now(),
+            dark,
+            shown: None,
+        }
+    }
+
+    pub fn set_dark(&mut self, dark: bool) {
+        self.dark = dark;
+    }
+
+    pub fn set_degraded(&mut self, degraded: bool) {
+        self.degraded = degraded;
+    }
+
+    /// A start (`to_running`) or stop was just issued; animate until the
+    /// daemon gets there.
+    pub fn begin(&mut self, to_running: bool, now: Instant) {
+        self.transition = Some(Transition {
+            to_running,
+            deadline: now + TRANSITION_TIMEOUT,
+        });
+        self.error_while = None;
+        self.frame = 0;
+        self.frame_at = now;
+    }
+
+    /// A start or stop failed outright.
+    pub fn fail(&mut self, running: bool) {
+        self.transition = None;
+        self.error_while = Some(running);
+    }
+
+    pub fn state(&mut self, running: bool, paused: bool, now: Instant) -> IconState {
+        if let Some(transition) = &self.transition {
+            if running == transition.to_running {
+                self.transition = None;
+            } else if now >= transition.deadline {
+                self.transition = None;
+                self.error_while = Some(running);
+            } else if transition.to_running {
+                return IconState::Starting;
+            } else {
+                return IconState::Stopping;
+            }
+        }
+
+        match self.error_while {
+            Some(was_running) if was_running == running => return IconState::Error,
+            Some(_) => self.error_while = None,
+            None => {}
+        }
+
+        if running && self.degraded {
+            IconState::Degraded
+        } else if running {
+            IconState::Enabled
+        } else if paused {
+            IconState::Paused
+        } else {
+            IconState::Disabled
+        }
+    }
+
+    /// The icon to show now and whether it's a template, or `None` if the
+    /// tray already shows it.
+    pub fn next(&mut self, icons: &IconSet, running: bool, paused: bool, now: Instant) -> Option<(Icon, bool)> {
+        let state = self.state(running, paused, now);
+        let frames = icons.frame_count(state);
+        if frames > 1 && now.duration_since(self.frame_at) >= icons.frame_interval() {
+            self.frame = (self.frame + 1) % frames;
+            self.frame_at = now;
+        }
+
+        let key = (state, self.frame % frames, self.dark, icons.height);
+        if self.shown == Some(key) {
+            return None;
+        }
+        self.shown = Some(key);
+        Some((icons.icon(state, key.1, self.dark), icons.is_template(state)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(n: u64) -> Duration {
+        Duration::from_secs(n)
+    }
+
+    #[test]
+    fn animates_until_the_daemon_gets_there() {
+        let now = Instant::now();
+        let mut display = IconDisplay::new(false);
+        assert_eq!(display.state(false, false, now), IconState::Disabled);
+
+        display.begin(true, now);
+        assert_eq!(display.state(false, false, now + secs(1)), IconState::Starting);
+        assert_eq!(display.state(true, false, now + secs(2)), IconState::Enabled);
+
+        display.begin(false, now + secs(3));
+        assert_eq!(display.state(true, false, now + secs(4)), IconState::Stopping);
+        assert_eq!(display.state(false, true, now + secs(5)), IconState::Paused);
+    }
+
+    #[test]
+    fn a_transition_that_times_out_shows_an_error() {
+        let now = Instant::now();
+        let mut display = IconDisplay::new(false);
+        display.begin(true, now);
+        assert_eq!(display.state(false, false, now + TRANSITION_TIMEOUT - secs(1)), IconState::Starting);
+        assert_eq!(display.state(false, false, now + TRANSITION_TIMEOUT), IconState::Error);
+        assert_eq!(display.state(false, false, now + TRANSITION_TIMEOUT + secs(30)), IconState::Error);
+        // It got there late after all
+        assert_eq!(display.state(true, false, now + TRANSITION_TIMEOUT + secs(31)), IconState::Enabled);
+    }
+
+    #[test]
+    fn fail_shows_an_error_until_the_state_changes_or_a_retry() {
+        let now = Instant::now();
+        let mut display = IconDisplay::new(false);
+        display.begin(false, now);
+        display.fail(true);
+        assert_eq!(display.state(true, false, now), IconState::Error);
+        assert_eq!(display.state(false, false, now), IconState::Disabled);
+        assert_eq!(display.state(true, false, now), IconState::Enabled);
+
+        display.fail(true);
+        display.begin(false, now);
+        assert_eq!(display.state(true, false, now), IconState::Stopping);
+    }
+
+    #[test]
+    fn degraded_only_while_running() {
+        let now = Instant::now();
+        let mut display = IconDisplay::new(false);
+        display.set_degraded(true);
+        assert_eq!(display.state(true, false, now), IconState::Degraded);
+        assert_eq!(display.state(false, false, now), IconState::Disabled);
+        // An error outranks it
+        display.fail(true);
+        assert_eq!(display.state(true, false, now), IconState::Error);
+        display.set_degraded(false);
+        display.begin(true, now);
+        assert_eq!(display.state(true, false, now), IconState::Enabled);
+    }
+
+    #[test]
+    fn next_advances_frames_and_skips_what_is_shown() {
+        let icons = IconSet::render(&IconTheme::builtin(IconTints::default()), pixel_height(1.0));
+        let interval = icons.frame_interval();
+        let now = Instant::now();
+        let mut display = IconDisplay::new(false);
+        assert!(display.next(&icons, false, false, now).is_some());
+        assert!(display.next(&icons, false, false, now).is_none());
+
+        display.begin(true, now);
+        assert!(display.next(&icons, false, false, now).is_some());
+        assert!(display.next(&icons, false, false, now + interval / 2).is_none());
+        assert!(display.next(&icons, false, false, now + interval).is_some());
+        assert_eq!(display.frame, 1);
+
+        assert!(display.next(&icons, true, false, now + interval).is_some());
+        // A dark menu bar needs the other image
+        display.set_dark(true);
+        assert!(display.next(&icons, true, false, now + interval).is_some());
+    }
+}
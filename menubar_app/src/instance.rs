@@ -0,0 +1,234 @@
+use std::fs::File;
+#[cfg(unix)]
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+#[cfg(unix)]
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::time::Duration;
+
+const LOCK_FILE: &str = "lapsus-menubar.lock";
+#[cfg(unix)]
+const SOCKET_FILE: &str = "lapsus-menubar.sock";
+
+/// Command-line flags another launch can hand to the running instance,
+/// with the menu command each one runs.
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("--enable", "enable"),
+    ("--disable", "disable"),
+    ("--toggle", "toggle"),
+    ("--restart", "restart"),
+    ("--preferences", "preferences"),
+    ("--quit", "quit"),
+];
+
+/// The menu command for the first flag in `COMMANDS`; other arguments,
+/// e.g. `--lang`, are left alone.
+pub fn command_arg(mut args: impl Iterator<Item = String>) -> Option<&'static str> {
+    args.find_map(|arg| COMMANDS.iter().find(|(flag, _)| *flag == arg).map(|(_, command)| *command))
+}
+
+/// Result of `claim`.
+pub enum Claim {
+    /// No other instance is running; this one carries on
+    Primary(InstanceGuard),
+    /// Another instance is running and was handed the command
+    Forwarded,
+    /// Another instance is running but the command couldn't be handed to it
+    Unreachable(Box<dyn std::error::Error>),
+}
+
+/// Held by the running instance: a lock file, and on Unix a socket that
+/// later launches send their command to.
+pub struct InstanceGuard {
+    _lock: File,
+    #[cfg(unix)]
+    listener: UnixListener,
+    #[cfg(unix)]
+    socket_path: PathBuf,
+}
+
+/// Becomes the one running instance, or hands `command` to the instance
+/// that already is.
+pub fn claim(command: Option<&str>) -> Result<Claim, Box<dyn std::error::Error>> {
+    // XDG_RUNTIME_DIR on Linux; $TMPDIR is already per user on macOS
+    claim_in(&dirs::runtime_dir().unwrap_or_else(std::env::temp_dir), command)
+}
+
+fn claim_in(dir: &Path, command: Option<&str>) -> Result<Claim, Box<dyn std::error::Error>> {
+    let lock = File::create(dir.join(LOCK_FILE))?;
+    match lock.try_lock() {
+        Ok(()) => {}
+        Err(std::fs::TryLockError::WouldBlock) => {
+            return Ok(match forward(dir, command) {
+                Ok(()) => Claim::Forwarded,
+                Err(e) => Claim::Unreachable(e),
+            });
+        }
+        Err(std::fs::TryLockError::Error(e)) => return Err(e.into()),
+    }
+
+    // The lock is released when a process dies, the socket file is not
+    #[cfg(unix)]
+    {
+        let socket_path = dir.join(SOCKET_FILE);
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = UnixListener::bind(&socket_path)?;
+        listener.set_nonblocking(true)?;
+        Ok(Claim::Primary(InstanceGuard { _lock: lock, listener, socket_path }))
+    }
+
+    #[cfg(not(unix))]
+    {
+        Ok(Claim::Primary(InstanceGuard { _lock: lock }))
+    }
+}
+
+#[cfg(unix)]
+fn forward(dir: &Path, command: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    // The running instance may have just taken the lock and not be
+    // listening yet
+    let socket_path = dir.join(SOCKET_FILE);
+    let mut attempts = 0;
+    let mut stream = loop {
+        match UnixStream::connect(&socket_path) {
+            Ok(stream) => break stream,
+            Err(_) if attempts < 20 => {
+                attempts += 1;
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            Err(e) => return Err(format!("Cannot reach the running instance: {}", e).into()),
+        }
+    };
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    writeln!(stream, "{}", command.unwrap_or_default())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    match reply.trim() {
+        "ok" => Ok(()),
+        other => Err(format!("The running instance refused {:?}: {}", command, other).into()),
+    }
+}
+
+#[cfg(not(unix))]
+fn forward(_dir: &Path, command: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        None => Ok(()),
+        Some(command) => Err(format!("Cannot hand {:?} to the running instance on this platform", command).into()),
+    }
+}
+
+impl InstanceGuard {
+    /// A command sent by a later launch, if one is waiting. Call
+    /// regularly from the event loop.
+    #[cfg(unix)]
+    pub fn poll(&self) -> Option<&'static str> {
+        loop {
+            let (stream, _) = self.listener.accept().ok()?;
+            match Self::receive(stream) {
+                Ok(Some(command)) => return Some(command),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to read from another launch: {}", e),
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn poll(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Reads one command line and answers it; an empty line is a plain
+    /// second launch.
+    #[cfg(unix)]
+    fn receive(mut stream: UnixStream) -> Result<Option<&'static str>, Box<dyn std::error::Error>> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line)?;
+
+        let line = line.trim();
+        let command = COMMANDS.iter().map(|(_, command)| *command).find(|command| *command == line);
+        if command.is_none() && !line.is_empty() {
+            writeln!(stream, "unknown command")?;
+            return Ok(None);
+        }
+        writeln!(stream, "ok")?;
+        Ok(command)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for InstanceGuard {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.socket_path);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> impl Iterator<Item = String> {
+        list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn reads_the_command_from_arguments() {
+        assert_eq!(command_arg(args(&["--lang", "de", "--enable"])), Some("enable"));
+        assert_eq!(command_arg(args(&["--quit", "--enable"])), Some("quit"));
+        assert_eq!(command_arg(args(&["--lang=de"])), None);
+    }
+
+    #[test]
+    fn second_launch_hands_over_its_command() {
+        let dir = std::env::temp_dir().join(format!("lapsus-instance-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let Ok(Claim::Primary(guard)) = claim_in(&dir, None) else {
+            panic!("first launch should be the primary instance");
+        };
+        assert!(guard.poll().is_none());
+
+        let second = {
+            let dir = dir.clone();
+            std::thread::spawn(move || {
+                claim_in(&dir, Some("enable"))
+                    .map(|claim| matches!(claim, Claim::Forwarded))
+                    .map_err(|e| e.to_string())
+            })
+        };
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        let received = loop {
+            if let Some(command) = guard.poll() {
+                break command;
+            }
+            assert!(std::time::Instant::now() < deadline, "the command never arrived");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(received, "enable");
+        assert!(second.join().unwrap().unwrap());
+
+        // Once the first instance is gone the next launch takes over
+        drop(guard);
+        assert!(matches!(claim_in(&dir, None), Ok(Claim::Primary(_))));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn an_instance_that_never_answers_is_unreachable() {
+        let dir = std::env::temp_dir().join(format!("lapsus-instance-mute-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // Holds the lock without listening, like an instance that hangs
+        let lock = File::create(dir.join(LOCK_FILE)).unwrap();
+        lock.try_lock().unwrap();
+        assert!(matches!(claim_in(&dir, Some("enable")), Ok(Claim::Unreachable(_))));
+
+        drop(lock);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::level_filters::LevelFilter;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, Registry};
+
+/// Log files are rotated daily; older ones beyond this are deleted.
+const KEEP_FILES: usize = 7;
+
+/// How much the app logs; libraries only log warnings and errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    /// In menu order.
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    /// Name in the config, also used in menu item ids.
+    pub fn id(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|level| level.id() == id)
+    }
+
+    fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// Handle on the installed subscriber, for changing the level while
+/// running.
+pub struct Logging {
+    handle: reload::Handle<Targets, Registry>,
+}
+
+impl Logging {
+    pub fn set_level(&self, level: LogLevel) {
+        if let Err(e) = self.handle.modify(|targets| *targets = targets_for(level)) {
+            tracing::warn!("Failed to change the log level: {}", e);
+        }
+    }
+}
+
+/// `~/Library/Logs/Lapsus Control` on macOS, the XDG state directory
+/// elsewhere.
+pub fn log_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "macos")]
+    let dir = dirs::home_dir().map(|home| home.join("Library/Logs").join(crate::APP_NAME));
+
+    #[cfg(not(target_os = "macos"))]
+    let dir = dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join("lapsus-menubar"));
+
+    dir
+}
+
+/// Logs to stderr, a rotating JSON file in `log_dir`, and with `system` to
+/// the unified log (macOS) or the journal (Linux). Call once, first thing.
+pub fn init(level: LogLevel, system: bool) -> Logging {
+    let (filter, handle) = reload::Layer::new(targets_for(level));
+
+    // Reported once the subscriber is up
+    let mut problems = Vec::new();
+    let mut layers: Vec<Box<dyn Layer<Registry> + Send + Sync>> =
+        vec![fmt::layer().with_writer(std::io::stderr).boxed()];
+    match log_file() {
+        // One JSON object per line, with the fields and spans of each event
+        Ok(file) => layers.push(fmt::layer().json().with_writer(file).boxed()),
+        Err(e) => problems.push(format!("Logging to stderr only: {}", e)),
+    }
+    if system {
+        match system_layer() {
+            Ok(layer) => layers.push(layer),
+            Err(e) => problems.push(format!("System log unavailable: {}", e)),
+        }
+    }
+
+    if let Err(e) = tracing_subscriber::registry().with(layers.with_filter(filter)).try_init() {
+        eprintln!("Logging already set up: {}", e);
+    }
+    for problem in problems {
+        tracing::warn!("{}", problem);
+    }
+    Logging { handle }
+}
+
+fn targets_for(level: LogLevel) -> Targets {
+    Targets::new()
+        .with_target(env!("CARGO_CRATE_NAME"), level.filter())
+        .with_default(level.filter().min(LevelFilter::WARN))
+}
+
+fn log_file() -> Result<RollingFileAppender, Box<dyn std::error::Error>> {
+    let dir = log_dir().ok_or("Cannot find a log directory")?;
+    std::fs::create_dir_all(&dir)?;
+    let file = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("lapsus-menubar")
+        .filename_suffix("log")
+        .max_log_files(KEEP_FILES)
+        .build(dir)?;
+    Ok(file)
+}
+
+#[cfg(target_os = "macos")]
+fn system_layer() -> Result<Box<dyn Layer<Registry> + Send + Sync>, Box<dyn std::error::Error>> {
+    Ok(tracing_oslog::OsLogger::new(crate::service::MENUBAR_ID, "default").boxed())
+}
+
+#[cfg(target_os = "linux")]
+fn system_layer() -> Result<Box<dyn Layer<Registry> + Send + Sync>, Box<dyn std::error::Error>> {
+    Ok(tracing_journald::layer()?.boxed())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn system_layer() -> Result<Box<dyn Layer<Registry> + Send + Sync>, Box<dyn std::error::Error>> {
+    Err("not supported on this platform".into())
+}
@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::service::{LoginBackend, LoginEntry};
+
+/// How often the login item is checked against the config while running.
+/// Asking System Events is slow, so not on every poll.
+pub const RECONCILE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Which side wins when the config and the OS disagree about what starts
+/// at login.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginItemPolicy {
+    /// The OS: e.g. removing the login item in System Settings unticks the box
+    #[default]
+    System,
+    /// The config: the login item is put back (or removed) to match it
+    Config,
+}
+
+/// What `reconcile` changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reconciliation {
+    Unchanged,
+    /// The OS disagreed and won; store this in the config
+    ConfigUpdated(bool),
+    /// The OS disagreed and was changed to match the config
+    SystemUpdated(bool),
+    /// The login item pointed at another program, e.g. a moved copy of
+    /// the app, and was replaced
+    Repaired,
+}
+
+/// Something started at login, kept in line with a config setting.
+pub struct LoginItem {
+    backend: Box<dyn LoginBackend>,
+    entry: LoginEntry,
+}
+
+impl LoginItem {
+    pub fn new(backend: Box<dyn LoginBackend>, entry: LoginEntry) -> Self {
+        Self { backend, entry }
+    }
+
+    pub fn set_enabled(&self, enable: bool) -> Result<(), Box<dyn std::error::Error>> {
+        self.backend.set_enabled(&self.entry, enable)
+    }
+
+    /// Moves a registration made through `previous`, e.g. before the
+    /// backend setting changed, to this item's backend. The old one is
+    /// removed first so the app never starts twice. Returns true if there
+    /// was one.
+    pub fn migrate_from(&self, previous: &dyn LoginBackend) -> Result<bool, Box<dyn std::error::Error>> {
+        if !previous.is_enabled(&self.entry)? {
+            return Ok(false);
+        }
+        previous.set_enabled(&self.entry, false)?;
+        self.set_enabled(true)?;
+        Ok(true)
+    }
+
+    /// Point at a new program or arguments; the next `reconcile` updates
+    /// an existing registration.
+    pub fn set_entry(&mut self, entry: LoginEntry) {
+        self.entry = entry;
+    }
+
+    /// Brings the OS login item and `wanted` (the config) in line, and
+    /// re-registers a login item left behind by a moved app. A backend
+    /// that needs permission to be read is left alone until `wanted`.
+    pub fn reconcile(
+        &self,
+        wanted: bool,
+        policy: LoginItemPolicy,
+    ) -> Result<Reconciliation, Box<dyn std::error::Error>> {
+        if !wanted && self.backend.needs_permission() {
+            return Ok(Reconciliation::Unchanged);
+        }
+        let enabled = self.backend.is_enabled(&self.entry)?;
+
+        if enabled != wanted {
+            return match policy {
+                LoginItemPolicy::System => Ok(Reconciliation::ConfigUpdated(enabled)),
+                LoginItemPolicy::Config => {
+                    self.set_enabled(wanted)?;
+                    Ok(Reconciliation::SystemUpdated(wanted))
+                }
+            };
+        }
+
+        let stale = enabled
+            && self
+                .backend
+                .registered_program(&self.entry)
+                .is_some_and(|path| !points_to(&path, &self.entry.program));
+        if stale {
+            self.backend.repair(&self.entry)?;
+            return Ok(Reconciliation::Repaired);
+        }
+        Ok(Reconciliation::Unchanged)
+    }
+}
+
+/// True if a login item at `registered` launches `exe`, allowing for the
+/// item being the .app bundle around it.
+fn points_to(registered: &Path, exe: &Path) -> bool {
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let (registered, exe) = (canonical(registered), canonical(exe));
+    registered == exe || (registered.extension().is_some_and(|ext| ext == "app") && exe.starts_with(&registered))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    type Shared<T> = Arc<Mutex<T>>;
+
+    /// A backend whose registration lives in memory; `log` records every
+    /// change across backends sharing it.
+    struct FakeBackend {
+        name: &'static str,
+        registered: Shared<Option<PathBuf>>,
+        log: Shared<Vec<String>>,
+        needs_permission: bool,
+    }
+
+    impl FakeBackend {
+        fn new(name: &'static str, registered: Option<&str>, log: &Shared<Vec<String>>) -> Self {
+            Self {
+                name,
+                registered: Arc::new(Mutex::new(registered.map(PathBuf::from))),
+                log: log.clone(),
+                needs_permission: false,
+            }
+        }
+    }
+
+    impl LoginBackend for FakeBackend {
+        fn is_enabled(&self, _entry: &LoginEntry) -> Result<bool, Box<dyn std::error::Error>> {
+            Ok(self.registered.lock().unwrap().is_some())
+        }
+
+        fn set_enabled(&self, entry: &LoginEntry, enable: bool) -> Result<(), Box<dyn std::error::Error>> {
+            *self.registered.lock().unwrap() = enable.then(|| entry.program.clone());
+            self.log.lock().unwrap().push(format!("{} {}", self.name, if enable { "on" } else { "off" }));
+            Ok(())
+        }
+
+        fn registered_program(&self, _entry: &LoginEntry) -> Option<PathBuf> {
+            self.registered.lock().unwrap().clone()
+        }
+
+        fn needs_permission(&self) -> bool {
+            self.needs_permission
+        }
+    }
+
+    fn entry() -> LoginEntry {
+        LoginEntry {
+            id: "com.example.app",
+            unit: "example-app",
+            name: "Example".to_string(),
+            program: PathBuf::from("/opt/example/app"),
+            args: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn migrate_removes_the_old_registration_first() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let new = FakeBackend::new("new", None, &log);
+        let registered = new.registered.clone();
+        let item = LoginItem::new(Box::new(new), entry());
+
+        let previous = FakeBackend::new("old", Some("/opt/example/app"), &log);
+        assert!(item.migrate_from(&previous).unwrap());
+        assert_eq!(*log.lock().unwrap(), ["old off", "new on"]);
+        assert!(previous.registered.lock().unwrap().is_none());
+        assert_eq!(registered.lock().unwrap().as_deref(), Some(std::path::Path::new("/opt/example/app")));
+
+        // Nothing left to move
+        assert!(!item.migrate_from(&previous).unwrap());
+        assert_eq!(log.lock().unwrap().len(), 2);
+    }
+
+    fn item(registered: Option<&str>) -> (LoginItem, Shared<Option<PathBuf>>, Shared<Vec<String>>) {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let backend = FakeBackend::new("os", registered, &log);
+        let state = backend.registered.clone();
+        (LoginItem::new(Box::new(backend), entry()), state, log)
+    }
+
+    #[test]
+    fn the_system_policy_takes_the_os_side() {
+        let (removed, _, _) = item(None);
+        assert_eq!(removed.reconcile(true, LoginItemPolicy::System).unwrap(), Reconciliation::ConfigUpdated(false));
+
+        let (added, state, log) = item(Some("/opt/example/app"));
+        assert_eq!(added.reconcile(false, LoginItemPolicy::System).unwrap(), Reconciliation::ConfigUpdated(true));
+        // Only the config changes
+        assert!(state.lock().unwrap().is_some());
+        assert!(log.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn the_config_policy_changes_the_os() {
+        let (item, state, _) = item(None);
+        assert_eq!(item.reconcile(true, LoginItemPolicy::Config).unwrap(), Reconciliation::SystemUpdated(true));
+        assert!(state.lock().unwrap().is_some());
+        assert_eq!(item.reconcile(false, LoginItemPolicy::Config).unwrap(), Reconciliation::SystemUpdated(false));
+        assert!(state.lock().unwrap().is_none());
+        assert_eq!(item.reconcile(false, LoginItemPolicy::Config).unwrap(), Reconciliation::Unchanged);
+    }
+
+    #[test]
+    fn a_registration_for_another_copy_is_repaired() {
+        let (item, state, log) = item(Some("/Volumes/Old/app"));
+        assert_eq!(item.reconcile(true, LoginItemPolicy::System).unwrap(), Reconciliation::Repaired);
+        assert_eq!(state.lock().unwrap().as_deref(), Some(Path::new("/opt/example/app")));
+        assert_eq!(*log.lock().unwrap(), ["os off", "os on"]);
+        assert_eq!(item.reconcile(true, LoginItemPolicy::System).unwrap(), Reconciliation::Unchanged);
+    }
+
+    #[test]
+    fn a_backend_needing_permission_is_only_read_once_wanted() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut backend = FakeBackend::new("os", Some("/Volumes/Old/app"), &log);
+        backend.needs_permission = true;
+        let item = LoginItem::new(Box::new(backend), entry());
+        assert_eq!(item.reconcile(false, LoginItemPolicy::System).unwrap(), Reconciliation::Unchanged);
+        assert_eq!(item.reconcile(false, LoginItemPolicy::Config).unwrap(), Reconciliation::Unchanged);
+        assert!(log.lock().unwrap().is_empty());
+        assert_eq!(item.reconcile(true, LoginItemPolicy::System).unwrap(), Reconciliation::Repaired);
+    }
+
+    #[test]
+    fn points_to_the_exe_or_the_bundle_around_it() {
+        let exe = Path::new("/Applications/Lapsus.app/Contents/MacOS/lapsus-menubar");
+        assert!(points_to(exe, exe));
+        assert!(points_to(Path::new("/Applications/Lapsus.app"), exe));
+        assert!(!points_to(Path::new("/Applications/Other.app"), exe));
+        assert!(!points_to(Path::new("/Applications/Lapsus"), exe));
+        assert!(!points_to(Path::new("/Volumes/Old/Lapsus.app/Contents/MacOS/lapsus-menubar"), exe));
+    }
+}
@@ -1,42 +1,305 @@
 use std::path::PathBuf;
-use std::process::{Command, Stdio};
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use sysinfo::{System, ProcessesToUpdate, Signal};
-use tray_icon::{TrayIconBuilder, menu::{Menu, MenuEvent, MenuItem, CheckMenuItem, PredefinedMenuItem, MenuId}};
+use std::process::{Child, Command, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use sysinfo::{Pid, System, ProcessesToUpdate, Signal};
+use tray_icon::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent, menu::MenuEvent};
 use tray_icon::Icon;
-use auto_launch::AutoLaunch;
+use tao::event::{Event, WindowEvent};
+use tao::event_loop::EventLoopWindowTarget;
 use serde::{Deserialize, Serialize};
 use std::fs;
 
+mod daemon;
+#[cfg(any(target_os = "macos", test))]
+mod dialog;
+mod foreground;
+mod health;
+mod hotkey;
+mod i18n;
+mod icons;
+mod instance;
+mod logging;
+mod login_item;
+mod metrics;
+mod notify;
+mod pause;
+mod power;
+mod preferences;
+mod rules;
+mod schedule;
+mod service;
+mod session;
+mod stats;
+mod supervisor;
+mod svg;
+mod tray_menu;
+
+use daemon::DaemonInfo;
+#[cfg(target_os = "macos")]
+use dialog::DialogKind;
+use foreground::ForegroundWatcher;
+use health::{HealthConfig, HealthMonitor};
+use hotkey::ToggleHotkey;
+use i18n::t;
+use icons::{IconCache, IconDisplay, IconTheme, IconTints};
+use instance::Claim;
+use logging::LogLevel;
+use login_item::{LoginItem, LoginItemPolicy, Reconciliation};
+use metrics::Metrics;
+use notify::{NotificationPolicy, Notifications};
+use pause::{Pause, PAUSE_MINUTES};
+use preferences::{PreferencesForm, PreferencesRequest, PreferencesWindow};
+use tray_menu::{ClickAction, MenuView, TrayMenu};
+use power::{PowerDecision, PowerPolicy, PowerPolicyEngine};
+use rules::{AppRule, RuleDecision, RulesEngine};
+use schedule::{ScheduleEntry, Scheduler, SystemClock};
+use service::{LinuxLoginBackend, LoginEntry};
+use session::{SessionDecision, SessionPolicy, SessionPolicyEngine};
+use stats::{EventKind, EventLog, Source};
+use supervisor::{Supervisor, SupervisorAction};
+
 const APP_NAME: &str = "Lapsus Control";
 const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 const CONFIG_FILE: &str = ".lapsus_menubar_config.json";
 
+/// What to do when the cursor moves to another display (macOS).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplaySwitchAction {
+    /// Restart lapsus_rust so it picks up the new display
+    #[default]
+    Restart,
+    Nothing,
+}
+
+/// What Quit does to lapsus_rust.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuitBehavior {
+    /// Stop lapsus_rust along with the menu bar app
+    #[default]
+    Stop,
+    /// Only hide the tray icon
+    LeaveRunning,
+}
+
+impl QuitBehavior {
+    /// What the alternate Quit item does.
+    fn other(self) -> Self {
+        match self {
+            QuitBehavior::Stop => QuitBehavior::LeaveRunning,
+            QuitBehavior::LeaveRunning => QuitBehavior::Stop,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 struct Config {
+    /// Start the menu bar app at login
     start_at_login: bool,
+    /// Start lapsus_rust at login, independently of the menu bar app
+    daemon_at_login: bool,
+    /// On launch, enable or disable lapsus_rust as it was last left
+    restore_state: bool,
+    /// Whether lapsus_rust was last enabled (true) or disabled (false) by
+    /// the user; automatic changes and quitting don't count
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_enabled: Option<bool>,
+    /// Whether Quit stops lapsus_rust or leaves it running
+    quit_behavior: QuitBehavior,
+    /// Whether the OS or the config wins when they disagree about what
+    /// starts at login
+    login_item_policy: LoginItemPolicy,
+    /// Start at Login on Linux: an XDG autostart entry or a systemd user unit
+    linux_login_backend: LinuxLoginBackend,
     #[serde(skip_serializing_if = "Option::is_none")]
     lapsus_rust_path: Option<String>,
+    /// Extra arguments when lapsus_rust is started directly
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    launch_args: Vec<String>,
+    /// Restart lapsus_rust or do nothing when the cursor changes display
+    display_switch: DisplaySwitchAction,
+    /// Enable/disable lapsus_rust while specific apps are frontmost
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    app_rules: Vec<AppRule>,
+    /// Time windows during which lapsus_rust is switched on or off
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    schedules: Vec<ScheduleEntry>,
+    /// What to do on wake, screen lock and user switching
+    session_policy: SessionPolicy,
+    /// Disable or lighten lapsus_rust on low battery / Low Power Mode
+    power_policy: PowerPolicy,
+    /// When a running lapsus_rust counts as hung or leaking
+    health: HealthConfig,
+    /// Left-click on the tray icon: toggle, open_menu or restart
+    primary_click: ClickAction,
+    /// Global shortcut for Enable/Disable, e.g. "cmd+shift+l"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    toggle_hotkey: Option<String>,
+    /// Directory with a custom tray icon theme
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon_theme: Option<String>,
+    /// Per-state colors for the tray icon, e.g. "#0a84ff" or "orange"
+    #[serde(skip_serializing_if = "IconTints::is_empty")]
+    icon_tints: IconTints,
+    /// Which errors, crashes and state changes raise a notification
+    notifications: NotificationPolicy,
+    /// How much the app logs; also set from the Log Level menu
+    log_level: LogLevel,
+    /// Also log to the unified log (macOS) or the journal (Linux)
+    system_log: bool,
+    /// Serve Prometheus metrics on 127.0.0.1 at this port
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metrics_port: Option<u16>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             start_at_login: false,
+            daemon_at_login: false,
+            restore_state: true,
+            last_enabled: None,
+            quit_behavior: QuitBehavior::Stop,
+            login_item_policy: LoginItemPolicy::default(),
+            linux_login_backend: LinuxLoginBackend::default(),
             lapsus_rust_path: None,
+            launch_args: Vec::new(),
+            display_switch: DisplaySwitchAction::Restart,
+            app_rules: Vec::new(),
+            schedules: Vec::new(),
+            session_policy: SessionPolicy::default(),
+            power_policy: PowerPolicy::default(),
+            health: HealthConfig::default(),
+            primary_click: ClickAction::default(),
+            toggle_hotkey: None,
+            icon_theme: None,
+            icon_tints: IconTints::default(),
+            notifications: NotificationPolicy::default(),
+            log_level: LogLevel::default(),
+            system_log: false,
+            metrics_port: None,
+        }
+    }
+}
+
+impl Config {
+    fn path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Ok(dirs::home_dir().ok_or("Cannot find home directory")?.join(CONFIG_FILE))
+    }
+
+    /// The config at `path`, or the defaults if there is none or it can't
+    /// be read.
+    fn load(path: &std::path::Path) -> Self {
+        if !path.exists() {
+            return Config::default();
+        }
+        let config = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()));
+        config.unwrap_or_else(|e| {
+            tracing::warn!("Ignoring unreadable config {:?}: {}", path, e);
+            Config::default()
+        })
+    }
+
+    /// Every problem with the settings, worded for the user.
+    fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        if let Some(path) = &self.lapsus_rust_path {
+            if !std::path::Path::new(path).is_file() {
+                errors.push(t!("config-no-file", setting = "lapsus_rust_path", path = path));
+            }
+        }
+        if let Some(accelerator) = &self.toggle_hotkey {
+            if let Err(e) = hotkey::parse(accelerator) {
+                errors.push(t!("config-problem", setting = "toggle_hotkey", problem = e));
+            }
+        }
+        if let Some(theme) = &self.icon_theme {
+            if !std::path::Path::new(theme).is_dir() {
+                errors.push(t!("config-no-directory", setting = "icon_theme", path = theme));
+            }
+        }
+        for color in self.icon_tints.invalid() {
+            errors.push(t!("config-bad-color", setting = "icon_tints", color = color));
+        }
+        for (i, entry) in self.schedules.iter().enumerate() {
+            if let Err(e) = entry.validate() {
+                errors.push(t!("config-problem", setting = format!("schedules[{}]", i), problem = e));
+            }
+        }
+        if let Some(threshold) = self.power_policy.battery_threshold.filter(|&t| t > 100) {
+            errors.push(t!("config-bad-threshold", setting = "power_policy", threshold = threshold));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn preferences_form(&self) -> PreferencesForm {
+        PreferencesForm {
+            lapsus_rust_path: self.lapsus_rust_path.clone().unwrap_or_default(),
+            launch_args: self.launch_args.clone(),
+            start_at_login: self.start_at_login,
+            daemon_at_login: self.daemon_at_login,
+            restore_state: self.restore_state,
+            quit_behavior: self.quit_behavior,
+            display_switch: self.display_switch,
+            toggle_hotkey: self.toggle_hotkey.clone().unwrap_or_default(),
+            notifications: self.notifications.clone(),
+        }
+    }
+
+    /// The config with the window's settings applied.
+    fn with_preferences(&self, form: PreferencesForm) -> Self {
+        let non_empty = |text: String| Some(text).filter(|text| !text.is_empty());
+        Self {
+            lapsus_rust_path: non_empty(form.lapsus_rust_path),
+            launch_args: form.launch_args,
+            start_at_login: form.start_at_login,
+            daemon_at_login: form.daemon_at_login,
+            restore_state: form.restore_state,
+            quit_behavior: form.quit_behavior,
+            display_switch: form.display_switch,
+            toggle_hotkey: non_empty(form.toggle_hotkey),
+            notifications: form.notifications,
+            ..self.clone()
         }
     }
 }
 
 #[derive(Clone)]
 struct AppState {
-    lapsus_path: PathBuf,
+    /// Replaced when the path is changed in Preferences
+    lapsus_path: Arc<Mutex<PathBuf>>,
     config_path: PathBuf,
     config: Arc<Mutex<Config>>,
-    auto_launcher: Arc<Mutex<AutoLaunch>>,
-    icon_enabled: Icon,
-    icon_disabled: Icon,
+    /// The menu bar app's own login item
+    menubar_login: Arc<Mutex<LoginItem>>,
+    /// lapsus_rust's LaunchAgent, systemd unit or autostart entry
+    daemon_login: Arc<Mutex<LoginItem>>,
+    rules: Arc<Mutex<RulesEngine>>,
+    schedule: Arc<Mutex<Scheduler>>,
+    pause: Arc<Mutex<Pause>>,
+    power: Arc<Mutex<PowerPolicyEngine>>,
+    /// Kept between scans so sysinfo can compute CPU usage
+    system: Arc<Mutex<System>>,
+    /// lapsus_rust processes started directly, until they exit and are
+    /// reaped
+    children: Arc<Mutex<Vec<Child>>>,
+    supervisor: Arc<Mutex<Supervisor>>,
+    health: Arc<Mutex<HealthMonitor>>,
+    /// Starts, stops, restarts and crashes, for Statistics
+    stats: Arc<Mutex<EventLog>>,
+    /// Last poll's findings, for `/metrics`
+    metrics: Arc<Mutex<Metrics>>,
 }
 
 impl AppState {
@@ -103,7 +366,7 @@ impl AppState {
             }
         }
 
-        Err("lapsus_rust not found in any expected location. Please set the path in config or bundle it with the app.".into())
+        Err(t!("error-not-found-anywhere").into())
     }
 
     fn new() -> Result<Self, Box<dyn std::error::Error>> {
@@ -114,51 +377,82 @@ impl AppState {
         // Try multiple locations for lapsus_rust
         let lapsus_path = Self::find_lapsus_rust(app_dir)?;
 
-        let config_path = dirs::home_dir()
-            .ok_or("Cannot find home directory")?
-            .join(CONFIG_FILE);
-
-        // Load or create config
-        let config = if config_path.exists() {
-            let content = fs::read_to_string(&config_path)?;
-            serde_json::from_str(&content).unwrap_or_default()
-        } else {
-            Config::default()
-        };
+        let config_path = Config::path()?;
+        let config = Config::load(&config_path);
+        // Problems are reported but not fatal; each feature falls back on
+        // its own when its setting can't be used
+        if let Err(errors) = config.validate() {
+            for error in errors {
+                tracing::warn!("Config: {}", error);
+            }
+        }
 
-        // Setup auto-launch
-        let auto_launcher = AutoLaunch::new(
-            APP_NAME,
-            &current_exe.to_string_lossy(),
-            false, // use_launch_agent
-            &[] as &[&str],
+        // What starts at login, each through its own backend
+        let menubar_login = LoginItem::new(
+            service::menubar_backend(config.linux_login_backend),
+            LoginEntry {
+                id: service::MENUBAR_ID,
+                unit: service::MENUBAR_UNIT,
+                name: APP_NAME.to_string(),
+                program: current_exe.clone(),
+                args: Vec::new(),
+            },
         );
+        let daemon_login = LoginItem::new(service::daemon_backend(), daemon_entry(&lapsus_path, &config));
 
-        // Load icons
-        let icons_dir = if current_exe.to_string_lossy().contains(".app/Contents/MacOS") {
-            // Running from app bundle
-            current_exe.parent()
-                .and_then(|p| p.parent())
-                .map(|p| p.join("Resources"))
-                .unwrap_or_else(|| app_dir.join("../icons"))
-        } else {
-            // Running from cargo build
-            app_dir.join("../icons")
-        };
-        
-        let icon_enabled = load_icon(icons_dir.join("cursor_enabled.png"))?;
-        let icon_disabled = load_icon(icons_dir.join("cursor_disabled.png"))?;
+        // Older versions registered through `auto-launch`, under the app's
+        // name; move that registration to the new entry
+        #[cfg(target_os = "linux")]
+        if service::remove_legacy_autostart(APP_NAME) {
+            if let Err(e) = menubar_login.set_enabled(true) {
+                tracing::warn!("Failed to replace old autostart entry: {}", e);
+            }
+        }
+
+        // A changed `linux_login_backend` takes the registration with it
+        for previous in service::previous_menubar_backends(config.linux_login_backend) {
+            match menubar_login.migrate_from(previous.as_ref()) {
+                Ok(true) => tracing::info!("Moved the login registration from the previous backend"),
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to move the login registration: {}", e),
+            }
+        }
+
+        let rules = RulesEngine::new(config.app_rules.clone());
+        let schedule = Scheduler::new(&config.schedules, Box::new(SystemClock));
+        let power = PowerPolicyEngine::new(config.power_policy.clone());
+        let health = HealthMonitor::new(config.health.clone());
 
         Ok(Self {
-            lapsus_path,
+            lapsus_path: Arc::new(Mutex::new(lapsus_path)),
             config_path,
             config: Arc::new(Mutex::new(config)),
-            auto_launcher: Arc::new(Mutex::new(auto_launcher)),
-            icon_enabled,
-            icon_disabled,
+            menubar_login: Arc::new(Mutex::new(menubar_login)),
+            daemon_login: Arc::new(Mutex::new(daemon_login)),
+            rules: Arc::new(Mutex::new(rules)),
+            schedule: Arc::new(Mutex::new(schedule)),
+            pause: Arc::new(Mutex::new(Pause::default())),
+            power: Arc::new(Mutex::new(power)),
+            system: Arc::new(Mutex::new(System::new())),
+            children: Arc::new(Mutex::new(Vec::new())),
+            supervisor: Arc::new(Mutex::new(Supervisor::new(false))),
+            health: Arc::new(Mutex::new(health)),
+            stats: Arc::new(Mutex::new(
+                stats::default_path().map_or_else(EventLog::in_memory, EventLog::open),
+            )),
+            metrics: Arc::new(Mutex::new(Metrics::new())),
         })
     }
 
+    /// Searches for lapsus_rust again, e.g. after its path was changed.
+    #[tracing::instrument(skip(self), err(level = "debug"))]
+    fn relocate_lapsus_rust(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let current_exe = std::env::current_exe()?;
+        let app_dir = current_exe.parent().ok_or("Cannot get parent directory")?;
+        *self.lapsus_path.lock().unwrap() = Self::find_lapsus_rust(app_dir)?;
+        Ok(())
+    }
+
     fn save_config(&self) -> Result<(), Box<dyn std::error::Error>> {
         let config = self.config.lock().unwrap();
         let content = serde_json::to_string_pretty(&*config)?;
@@ -166,126 +460,161 @@ impl AppState {
         Ok(())
     }
 
-    fn is_lapsus_running(&self) -> bool {
-        // First check if launchd service is loaded
-        if let Ok(output) = Command::new("launchctl")
-            .args(["list", "com.lapsus.rust"])
-            .output()
-        {
-            if output.status.success() {
-                // Service exists, check if it's running (has a PID)
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                // Output format: "PID\tStatus\tLabel" or "-\tStatus\tLabel"
-                // If first field is a number (PID), it's running
-                if let Some(first_line) = stdout.lines().next() {
-                    if let Some(pid_str) = first_line.split_whitespace().next() {
-                        if pid_str.parse::<i32>().is_ok() {
-                            return true;
-                        }
-                    }
-                }
-            }
-        }
-        
-        // Fallback: check process list (for manual starts)
-        let mut sys = System::new_all();
-        sys.refresh_processes(ProcessesToUpdate::All, true);
-        
-        sys.processes().values().any(|process| {
-            let name = process.name().to_string_lossy();
-            name.contains("lapsus_rust") || name == "lapsus_rust"
-        })
+    fn daemon_info(&self) -> Option<DaemonInfo> {
+        self.reap_children();
+        daemon::find(&mut self.system.lock().unwrap())
     }
 
-    fn start_lapsus(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // Check if LaunchAgent exists
-        let plist_path = dirs::home_dir()
-            .ok_or("Cannot find home directory")?
-            .join("Library/LaunchAgents/com.lapsus.rust.plist");
-        
-        if plist_path.exists() {
-            // Use launchctl to load the service
-            let output = Command::new("launchctl")
-                .args(["load", plist_path.to_str().unwrap()])
-                .output()?;
-            
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                // Ignore "already loaded" errors
-                if !stderr.contains("Already loaded") && !stderr.contains("service already loaded") {
-                    return Err(format!("Failed to load service: {}", stderr).into());
-                }
+    /// Collects the exit status of directly started lapsus_rust processes
+    /// that have ended, so they don't linger as zombies.
+    fn reap_children(&self) {
+        self.children.lock().unwrap().retain_mut(|child| match child.try_wait() {
+            Ok(None) => true,
+            Ok(Some(status)) => {
+                tracing::debug!(pid = child.id(), "lapsus_rust exited: {}", status);
+                false
             }
-            
-            Ok(())
-        } else {
-            // Fallback: start process directly if no LaunchAgent
-            if !self.lapsus_path.exists() {
-                return Err(format!("lapsus_rust not found at: {:?}", self.lapsus_path).into());
+            Err(e) => {
+                tracing::warn!(pid = child.id(), "Failed to check on lapsus_rust: {}", e);
+                false
             }
+        });
+    }
+
+    fn is_lapsus_running(&self) -> bool {
+        self.daemon_info().is_some()
+    }
 
-            Command::new(&self.lapsus_path)
-                .stdin(Stdio::null())
-                .stdout(Stdio::null())
-                .stderr(Stdio::null())
-                .spawn()?;
+    #[tracing::instrument(skip(self), err(level = "debug"))]
+    fn start_lapsus(&self, source: Source) -> Result<(), Box<dyn std::error::Error>> {
+        self.start_daemon()?;
+        self.stats.lock().unwrap().record(EventKind::Enabled, source);
+        Ok(())
+    }
 
-            Ok(())
+    #[tracing::instrument(skip(self), err(level = "debug"))]
+    fn stop_lapsus(&self, source: Source) -> Result<(), Box<dyn std::error::Error>> {
+        self.stop_daemon()?;
+        self.stats.lock().unwrap().record(EventKind::Disabled, source);
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self), err(level = "debug"))]
+    fn restart_lapsus(&self, source: Source) -> Result<(), Box<dyn std::error::Error>> {
+        // Quick restart: disable and re-enable within ~50ms
+        self.stop_daemon()?;
+        std::thread::sleep(Duration::from_millis(50));
+        self.start_daemon()?;
+        self.stats.lock().unwrap().record(EventKind::Restarted, source);
+        Ok(())
+    }
+
+    fn start_daemon(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.supervisor.lock().unwrap().set_wanted(true);
+
+        let profile_args = self.power.lock().unwrap().profile_args().to_vec();
+
+        // Through the LaunchAgent or systemd unit when there is one, so
+        // launchd or systemd owns the process; either starts it whether or
+        // not it runs at login. Its arguments are fixed in the plist or
+        // unit, so while the light profile is on the process is started
+        // directly instead.
+        if daemon_service_exists() && profile_args.is_empty() {
+            #[cfg(target_os = "macos")]
+            return service::LaunchdBackend::start(service::DAEMON_ID);
+            #[cfg(target_os = "linux")]
+            return service::SystemdUserBackend::start(service::DAEMON_UNIT);
+        }
+
+        // Otherwise start the process directly
+        let lapsus_path = self.lapsus_path.lock().unwrap().clone();
+        if !lapsus_path.exists() {
+            return Err(t!("error-not-found", path = format!("{:?}", lapsus_path)).into());
+        }
+
+        let mut args = self.config.lock().unwrap().launch_args.clone();
+        args.extend(profile_args);
+        let mut command = Command::new(&lapsus_path);
+        command
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        // Its own process group, so it can outlive the menu bar app
+        // (`quit_behavior`) and a Ctrl-C in the app's terminal
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
         }
+        let child = command.spawn()?;
+        tracing::debug!(pid = child.id(), "Started {:?} {:?}", lapsus_path, args);
+        self.children.lock().unwrap().push(child);
+
+        Ok(())
     }
 
-    fn stop_lapsus(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // Check if LaunchAgent exists
-        let plist_path = dirs::home_dir()
-            .ok_or("Cannot find home directory")?
-            .join("Library/LaunchAgents/com.lapsus.rust.plist");
-        
-        if plist_path.exists() {
-            // Use launchctl to unload the service
-            let output = Command::new("launchctl")
-                .args(["unload", plist_path.to_str().unwrap()])
-                .output()?;
-            
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                // Ignore "not loaded" errors
-                if !stderr.contains("Could not find") && !stderr.contains("not loaded") {
-                    return Err(format!("Failed to unload service: {}", stderr).into());
-                }
-            }
-            
-            Ok(())
-        } else {
-            // Fallback: kill process directly if no LaunchAgent
-            let mut sys = System::new_all();
-            sys.refresh_processes(ProcessesToUpdate::All, true);
-            
-            let mut found = false;
-            for (_pid, process) in sys.processes() {
-                let name = process.name().to_string_lossy();
-                if name.contains("lapsus_rust") || name == "lapsus_rust" {
-                    if process.kill_with(Signal::Term).unwrap_or(false) {
-                        found = true;
-                    }
-                }
-            }
+    fn stop_daemon(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.supervisor.lock().unwrap().set_wanted(false);
+
+        #[cfg(target_os = "macos")]
+        let mut found = service::LaunchdBackend::stop(service::DAEMON_ID)?;
+        #[cfg(target_os = "linux")]
+        let mut found = service::SystemdUserBackend::unit_exists(service::DAEMON_UNIT)
+            && service::SystemdUserBackend::stop(service::DAEMON_UNIT)?;
+        #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+        let mut found = false;
 
-            if !found {
-                return Err("lapsus_rust process not found".into());
+        // Also a lapsus_rust started directly, e.g. before the LaunchAgent
+        // or unit existed: those in the last scan, and any this app started since.
+        // Only they are looked at again, in case one has exited and its PID
+        // been reused.
+        let mut system = self.system.lock().unwrap();
+        let mut pids: Vec<Pid> = system
+            .processes()
+            .iter()
+            .filter(|(_, process)| daemon::is_lapsus_process(process))
+            .map(|(pid, _)| *pid)
+            .collect();
+        pids.extend(self.children.lock().unwrap().iter().map(|child| Pid::from_u32(child.id())));
+        pids.sort();
+        pids.dedup();
+        system.refresh_processes(ProcessesToUpdate::Some(&pids), true);
+        for pid in pids {
+            let Some(process) = system.process(pid) else {
+                continue;
+            };
+            if daemon::is_lapsus_process(process) && process.kill_with(Signal::Term).unwrap_or(false) {
+                tracing::debug!(pid = pid.as_u32(), "Sent SIGTERM");
+                found = true;
             }
+        }
 
-            Ok(())
+        if !found {
+            return Err("lapsus_rust process not found".into());
+        }
+        Ok(())
+    }
+
+    /// Returns false if the decision was overruled and nothing changed.
+    fn apply_decision(&self, decision: RuleDecision, source: Source) -> Result<bool, Box<dyn std::error::Error>> {
+        // A timed pause or the power policy outranks rules and schedules
+        if decision == RuleDecision::Start
+            && (self.pause.lock().unwrap().is_active() || self.power.lock().unwrap().holds_disabled())
+        {
+            return Ok(false);
+        }
+
+        match decision {
+            RuleDecision::Start => self.start_lapsus(source)?,
+            RuleDecision::Stop => self.stop_lapsus(source)?,
         }
+        Ok(true)
     }
 
+    #[tracing::instrument(skip(self), err(level = "debug"))]
     fn toggle_auto_launch(&self, enable: bool) -> Result<(), Box<dyn std::error::Error>> {
-        let auto_launcher = self.auto_launcher.lock().unwrap();
-        
-        if enable {
-            auto_launcher.enable()?;
-        } else {
-            auto_launcher.disable()?;
-        }
+        self.menubar_login.lock().unwrap().set_enabled(enable)?;
 
         let mut config = self.config.lock().unwrap();
         config.start_at_login = enable;
@@ -295,155 +624,435 @@ impl AppState {
         Ok(())
     }
 
-    fn get_current_icon(&self) -> Icon {
-        if self.is_lapsus_running() {
-            // When running, show FILLED icon
-            self.icon_enabled.clone()
-        } else {
-            // When stopped, show OUTLINE icon
-            self.icon_disabled.clone()
+    /// Registers lapsus_rust at login, or not, as the config says; with
+    /// `changed` false an existing registration is only refreshed, e.g.
+    /// for a new path or arguments.
+    #[tracing::instrument(skip(self), err(level = "debug"))]
+    fn update_daemon_login(&self, changed: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let entry = daemon_entry(&self.lapsus_path.lock().unwrap(), &self.config.lock().unwrap());
+        let enable = self.config.lock().unwrap().daemon_at_login;
+
+        let had_service = daemon_service_exists();
+        let mut daemon_login = self.daemon_login.lock().unwrap();
+        daemon_login.set_entry(entry);
+        if changed || enable {
+            daemon_login.set_enabled(enable)?;
+        }
+        drop(daemon_login);
+        self.adopt_into_service(had_service)
+    }
+
+    /// Once the LaunchAgent or systemd unit has been created, moves a
+    /// lapsus_rust that was started directly under launchd or systemd, so
+    /// start and stop keep reaching it.
+    fn adopt_into_service(&self, had_service: bool) -> Result<(), Box<dyn std::error::Error>> {
+        if had_service || !daemon_service_exists() || !self.is_lapsus_running() {
+            return Ok(());
+        }
+        tracing::info!("Restarting lapsus_rust through its new LaunchAgent or unit");
+        self.stop_daemon()?;
+        std::thread::sleep(Duration::from_millis(50));
+        self.start_daemon()
+    }
+
+    /// Remembers what the user last asked for, for `restore_state`.
+    fn remember_enabled(&self, enabled: bool) {
+        self.config.lock().unwrap().last_enabled = Some(enabled);
+        if let Err(e) = self.save_config() {
+            tracing::error!("Failed to save config: {}", e);
+        }
+    }
+
+    /// Checks both login items against the config, following
+    /// `login_item_policy`. Slow (it may ask System Events), so the poll
+    /// runs it on a worker thread; what the OS won is returned for
+    /// `apply_login_changes`.
+    #[tracing::instrument(skip(self))]
+    fn reconcile_login_items(&self) -> LoginChanges {
+        let (menubar, daemon, policy) = {
+            let config = self.config.lock().unwrap();
+            (config.start_at_login, config.daemon_at_login, config.login_item_policy)
+        };
+
+        let menubar = self.menubar_login.lock().unwrap().reconcile(menubar, policy);
+        log_reconciliation("Start at Login", &menubar);
+        let had_service = daemon_service_exists();
+        let daemon = self.daemon_login.lock().unwrap().reconcile(daemon, policy);
+        log_reconciliation("Start lapsus_rust at login", &daemon);
+        if let Err(e) = self.adopt_into_service(had_service) {
+            tracing::warn!("Failed to move lapsus_rust under its LaunchAgent or unit: {}", e);
+        }
+
+        let config_updated = |result| match result {
+            Ok(Reconciliation::ConfigUpdated(enabled)) => Some(enabled),
+            _ => None,
+        };
+        LoginChanges {
+            start_at_login: config_updated(menubar),
+            daemon_at_login: config_updated(daemon),
+        }
+    }
+
+    /// Stores what `reconcile_login_items` found the OS to have won, and
+    /// saves the config if anything changed.
+    fn apply_login_changes(&self, changes: LoginChanges) {
+        if changes.start_at_login.is_none() && changes.daemon_at_login.is_none() {
+            return;
+        }
+        {
+            let mut config = self.config.lock().unwrap();
+            if let Some(enabled) = changes.start_at_login {
+                config.start_at_login = enabled;
+            }
+            if let Some(enabled) = changes.daemon_at_login {
+                config.daemon_at_login = enabled;
+            }
+        }
+        if let Err(e) = self.save_config() {
+            tracing::error!("Failed to save config: {}", e);
         }
     }
 }
 
-fn load_icon(path: PathBuf) -> Result<Icon, Box<dyn std::error::Error>> {
-    let image = image::open(&path)?;
-    let rgba = image.to_rgba8();
-    let (width, height) = rgba.dimensions();
-    
-    Icon::from_rgba(rgba.into_raw(), width, height)
-        .map_err(|e| format!("Failed to create icon: {:?}", e).into())
+/// Login item settings changed outside the app, as found by
+/// `AppState::reconcile_login_items`.
+#[derive(Debug, Default)]
+struct LoginChanges {
+    start_at_login: Option<bool>,
+    daemon_at_login: Option<bool>,
 }
 
-fn build_menu(state: &AppState) -> Result<Menu, Box<dyn std::error::Error>> {
-    let menu = Menu::new();
-    
-    let is_running = state.is_lapsus_running();
-    
-    // Enable/Disable items
-    if is_running {
-        let disable_item = MenuItem::with_id(
-            MenuId::new("disable"),
-            "Disable Lapsus",
-            true,
-            None
-        );
-        menu.append(&disable_item)?;
-    } else {
-        let enable_item = MenuItem::with_id(
-            MenuId::new("enable"),
-            "Enable Lapsus",
-            true,
-            None
-        );
-        menu.append(&enable_item)?;
+/// Whether lapsus_rust has a LaunchAgent (macOS) or systemd user unit
+/// (Linux), through which it is then started and stopped.
+fn daemon_service_exists() -> bool {
+    #[cfg(target_os = "macos")]
+    let exists = service::LaunchdBackend::plist_path(service::DAEMON_ID).is_some_and(|plist| plist.exists());
+
+    #[cfg(target_os = "linux")]
+    let exists = service::SystemdUserBackend::unit_exists(service::DAEMON_UNIT);
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    let exists = false;
+
+    exists
+}
+
+/// How lapsus_rust is registered to start at login.
+fn daemon_entry(lapsus_path: &std::path::Path, config: &Config) -> LoginEntry {
+    LoginEntry {
+        id: service::DAEMON_ID,
+        unit: service::DAEMON_UNIT,
+        name: "lapsus_rust".to_string(),
+        program: lapsus_path.to_path_buf(),
+        args: config.launch_args.clone(),
     }
-    
-    menu.append(&PredefinedMenuItem::separator())?;
-    
-    // Start at Login checkbox
-    let config = state.config.lock().unwrap();
-    let start_at_login = CheckMenuItem::with_id(
-        MenuId::new("start_at_login"),
-        "Start at Login",
-        true,
-        config.start_at_login,
-        None
-    );
-    drop(config);
-    menu.append(&start_at_login)?;
-    
-    menu.append(&PredefinedMenuItem::separator())?;
-    
-    // About
-    let about_item = MenuItem::with_id(
-        MenuId::new("about"),
-        "About",
-        true,
-        None
-    );
-    menu.append(&about_item)?;
-    
-    // Quit
-    let quit_item = MenuItem::with_id(
-        MenuId::new("quit"),
-        "Quit",
-        true,
-        None
+}
+
+fn log_reconciliation(setting: &str, result: &Result<Reconciliation, Box<dyn std::error::Error>>) {
+    match result {
+        Ok(Reconciliation::Unchanged) => {}
+        Ok(Reconciliation::ConfigUpdated(enabled)) => {
+            tracing::info!("{} changed outside the app, now {}", setting, enabled)
+        }
+        Ok(Reconciliation::SystemUpdated(enabled)) => {
+            tracing::info!("{}: restored to match the config ({})", setting, enabled)
+        }
+        Ok(Reconciliation::Repaired) => tracing::info!("{}: re-registered for the current location", setting),
+        Err(e) => tracing::warn!("{}: could not check the OS setting: {}", setting, e),
+    }
+}
+
+fn menu_view(state: &AppState, daemon: Option<&DaemonInfo>) -> MenuView {
+    let mut notes = Vec::new();
+
+    // Rule-driven override, if a frontmost-app rule is in control
+    if let Some(active) = state.rules.lock().unwrap().active() {
+        notes.push(active.label());
+    }
+
+    // Active schedule window
+    if let Some(label) = state.schedule.lock().unwrap().label() {
+        notes.push(label);
+    }
+
+    // Countdown while paused
+    if let Some(label) = state.pause.lock().unwrap().label(SystemTime::now()) {
+        notes.push(label);
+    }
+
+    // Failed health check
+    if let Some(problem) = state.health.lock().unwrap().problem() {
+        notes.push(t!("health-note", problem = problem));
+    }
+
+    MenuView {
+        status: daemon::status_text(daemon),
+        running: daemon.is_some(),
+        notes,
+        start_at_login: state.config.lock().unwrap().start_at_login,
+        quit_behavior: state.config.lock().unwrap().quit_behavior,
+        log_level: state.config.lock().unwrap().log_level,
+        statistics: stats::menu_rows(state.stats.lock().unwrap().events()),
+    }
+}
+
+fn tooltip_text(state: &AppState) -> String {
+    let mut tooltip = APP_NAME.to_string();
+    if let Some(label) = state.pause.lock().unwrap().label(SystemTime::now()) {
+        tooltip.push_str(&format!(" - {}", label));
+    }
+    if let Some(label) = state.power.lock().unwrap().label() {
+        tooltip.push_str(&format!(" - {}", label));
+    }
+    if let Some(problem) = state.health.lock().unwrap().problem() {
+        tooltip.push_str(&format!(" - {}", t!("health-note", problem = problem)));
+    }
+    tooltip
+}
+
+/// Updates menu and tooltip from one daemon scan.
+fn refresh_tray(state: &AppState, tray: &tray_icon::TrayIcon, tray_menu: &mut TrayMenu, daemon: Option<&DaemonInfo>) {
+    if let Err(e) = tray_menu.update(&menu_view(state, daemon)) {
+        tracing::warn!("Failed to update the menu: {}", e);
+    }
+    if let Err(e) = tray.set_tooltip(Some(tooltip_text(state))) {
+        tracing::warn!("Failed to set the tooltip: {}", e);
+    }
+}
+
+/// Plain `set_icon` clears the template flag on macOS, so set both together.
+fn show_icon(tray: &tray_icon::TrayIcon, icon: Icon, template: bool) {
+    #[cfg(target_os = "macos")]
+    let result = tray.set_icon_with_as_template(Some(icon), template);
+
+    #[cfg(not(target_os = "macos"))]
+    let result = {
+        let _ = template;
+        tray.set_icon(Some(icon))
+    };
+
+    if let Err(e) = result {
+        tracing::warn!("Failed to set the tray icon: {}", e);
+    }
+}
+
+/// Scale factor of the display showing the tray icon, else the primary one.
+fn tray_scale(tray: &tray_icon::TrayIcon, target: &EventLoopWindowTarget<()>) -> Option<f64> {
+    if let Some(rect) = tray.rect() {
+        let (x, y) = (rect.position.x, rect.position.y);
+        let monitor = target.available_monitors().find(|monitor| {
+            let (origin, size) = (monitor.position(), monitor.size());
+            x >= origin.x as f64
+                && x < origin.x as f64 + size.width as f64
+                && y >= origin.y as f64
+                && y < origin.y as f64 + size.height as f64
+        });
+        if let Some(monitor) = monitor {
+            return Some(monitor.scale_factor());
+        }
+    }
+    target.primary_monitor().map(|monitor| monitor.scale_factor())
+}
+
+/// Registers the toggle hotkey and shows it in the menu; `None` clears it.
+fn register_toggle_hotkey(
+    accelerator: Option<&str>,
+    tray_menu: &TrayMenu,
+    notifications: &mut Notifications,
+) -> Option<ToggleHotkey> {
+    let registered = accelerator.and_then(|accelerator| match ToggleHotkey::register(accelerator) {
+        Ok(registered) => Some(registered),
+        Err(e) => {
+            notifications.error(&t!("error-hotkey", error = e));
+            None
+        }
+    });
+    let shown = tray_menu.set_toggle_accelerator(
+        registered.as_ref().map(|registered| hotkey::menu_accelerator(registered.hotkey())),
     );
-    menu.append(&quit_item)?;
-    
-    Ok(menu)
+    if let Err(e) = shown {
+        tracing::warn!("Failed to show the hotkey in the menu: {}", e);
+    }
+    registered
 }
 
 fn show_about_dialog() {
     #[cfg(target_os = "macos")]
     {
-        use std::process::Command;
         let message = format!(
-            "{} - Version {}\\n\\nMenu bar app to control lapsus_rust\\n\\nhttps://github.com/margooey/lapsus_rust",
-            APP_NAME, APP_VERSION
-        );
-        
-        let script = format!(
-            "display dialog \"{}\" buttons {{\"OK\"}} default button \"OK\" with title \"About\"",
-            message
+            "{}\n\n{}\n\nhttps://github.com/margooey/lapsus_rust",
+            t!("dialog-about-version", app = APP_NAME, version = APP_VERSION),
+            t!("dialog-about-description")
         );
-        
-        let _ = Command::new("osascript")
-            .arg("-e")
-            .arg(&script)
-            .spawn();
+        dialog::show(DialogKind::Info, &t!("dialog-about-title"), &message);
     }
 }
 
 fn show_error_dialog(message: &str) {
     #[cfg(target_os = "macos")]
     {
-        use std::process::Command;
-        let escaped = message.replace("\"", "\\\"").replace("\n", "\\n");
-        let script = format!(
-            "display dialog \"{}\" buttons {{\"OK\"}} default button \"OK\" with title \"Error\" with icon stop",
-            escaped
-        );
-        let _ = Command::new("osascript")
-            .arg("-e")
-            .arg(&script)
-            .spawn();
+        dialog::show(DialogKind::Error, &t!("dialog-error-title"), message);
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        tracing::error!("{}", message);
     }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Logging first, at the configured level, so startup problems show up
+    let logging = {
+        let config = Config::path().map(|path| Config::load(&path)).unwrap_or_default();
+        logging::init(config.log_level, config.system_log)
+    };
+    tracing::info!(version = APP_VERSION, "Starting {}", APP_NAME);
+
+    // Pick the UI language before anything is shown
+    i18n::init(i18n::lang_arg(std::env::args().skip(1)).as_deref());
+
+    // `lapsus-menubar stats [days]` prints the history and exits
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(at) = args.iter().position(|arg| arg == "stats") {
+        let days = args.get(at + 1).and_then(|days| days.parse().ok()).unwrap_or(14);
+        let log = stats::default_path().map_or_else(EventLog::in_memory, EventLog::open);
+        print!("{}", stats::report(log.events(), days));
+        return Ok(());
+    }
+
+    // One tray icon per user: a second launch hands its command, e.g.
+    // `--enable`, to the running instance and exits
+    let mut launch_command = instance::command_arg(std::env::args().skip(1));
+    let instance = match instance::claim(launch_command) {
+        Ok(Claim::Primary(guard)) => Some(guard),
+        Ok(Claim::Forwarded) => {
+            tracing::info!("{} is already running", APP_NAME);
+            return Ok(());
+        }
+        Ok(Claim::Unreachable(e)) => {
+            // A second instance would fight the first over lapsus_rust
+            show_error_dialog(&t!("error-already-running", error = e));
+            return Err(e);
+        }
+        Err(e) => {
+            // No lock file, so no other instance to hand over to
+            tracing::warn!("Single-instance check failed: {}", e);
+            None
+        }
+    };
+
     // Initialize app state first to read config
     let state = match AppState::new() {
         Ok(s) => s,
         Err(e) => {
-            show_error_dialog(&format!("Failed to initialize app: {}", e));
+            show_error_dialog(&t!("error-init", error = e));
             return Err(e);
         }
     };
 
+    // Non-modal notifications for failures, crashes and state changes
+    let mut notifications = Notifications::new(state.config.lock().unwrap().notifications.clone());
+
     // Check if lapsus_rust exists
-    if !state.lapsus_path.exists() {
-        show_error_dialog(&format!(
-            "lapsus_rust not found at: {:?}\n\nPlease ensure lapsus_rust is in the correct location.",
-            state.lapsus_path
-        ));
+    let lapsus_path = state.lapsus_path.lock().unwrap().clone();
+    if !lapsus_path.exists() {
+        notifications.error(&t!("error-not-found", path = format!("{:?}", lapsus_path)));
+    }
+
+    // Prometheus endpoint, if configured
+    if let Some(port) = state.config.lock().unwrap().metrics_port {
+        if let Err(e) = metrics::serve(port, state.metrics.clone(), state.stats.clone()) {
+            notifications.error(&t!("error-metrics", port = port, error = e));
+        }
     }
 
+    // A login item may have been removed, or the app moved, since the
+    // last run
+    state.apply_login_changes(state.reconcile_login_items());
+    let mut last_login_check = Instant::now();
+    // The poll's own check, while it runs on its worker thread
+    let mut login_check: Option<mpsc::Receiver<LoginChanges>> = None;
+
     // Create event loop
     let event_loop = tao::event_loop::EventLoop::new();
 
-    // Build initial menu
-    let menu = build_menu(&state)?;
+    // Note anything that started or stopped lapsus_rust since the last run
+    let running = {
+        let daemon = state.daemon_info();
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        let started = daemon.as_ref().map(|daemon| {
+            let uptime = daemon.stats.as_ref().map_or(Duration::ZERO, |stats| stats.uptime);
+            now.saturating_sub(uptime).as_secs() as i64
+        });
+        state.stats.lock().unwrap().observe(started);
+        daemon.is_some()
+    };
+
+    // Bring lapsus_rust back the way the user last left it, whatever
+    // started or stopped it since. One started by `daemon_at_login` was
+    // asked for too, so it is left running.
+    let (restore, daemon_at_login) = {
+        let config = state.config.lock().unwrap();
+        (config.last_enabled.filter(|_| config.restore_state), config.daemon_at_login)
+    };
+    match restore {
+        Some(true) if !running => {
+            if let Err(e) = state.start_lapsus(Source::Launch) {
+                notifications.error(&t!("error-start", error = e));
+            }
+        }
+        Some(false) if running && !daemon_at_login => {
+            if let Err(e) = state.stop_lapsus(Source::Launch) {
+                notifications.error(&t!("error-stop", error = e));
+            }
+        }
+        _ => {}
+    }
+
+    // Build the menu once; later changes update it in place
+    let daemon = state.daemon_info();
+    // Adopt a lapsus_rust that is already running, e.g. left behind by
+    // Quit (Leave Lapsus Running), so a crash gets noticed
+    if let Some(daemon) = &daemon {
+        tracing::info!(pid = daemon.pid, "Adopting running lapsus_rust");
+    }
+    state.supervisor.lock().unwrap().set_wanted(daemon.is_some());
+    let mut tray_menu = TrayMenu::new(&menu_view(&state, daemon.as_ref()))?;
     
-    // Create tray icon
+    // Icons rendered for the display's scale factor, plus icon state,
+    // animation frame and menu bar appearance
+    let mut icon_cache = {
+        let config = state.config.lock().unwrap();
+        IconCache::new(IconTheme::load(
+            config.icon_theme.as_deref().map(std::path::Path::new),
+            config.icon_tints.clone(),
+        ))
+    };
+    let mut icon_scale = event_loop.primary_monitor().map_or(1.0, |monitor| monitor.scale_factor());
+    let appearance = icon_cache.follows_appearance().then(icons::Appearance::watch);
+    let mut icon_display = IconDisplay::new(appearance.as_ref().is_some_and(icons::Appearance::is_dark));
+    let (initial_icon, initial_template) = icon_display
+        .next(&icon_cache.get(icon_scale), daemon.is_some(), false, Instant::now())
+        .expect("nothing shown yet");
+
+    // Create tray icon; the menu opens on left click only when that's
+    // the configured primary action
+    let primary_click = state.config.lock().unwrap().primary_click;
     let tray_icon = TrayIconBuilder::new()
-        .with_menu(Box::new(menu))
-        .with_tooltip(APP_NAME)
-        .with_icon(state.get_current_icon())
+        .with_menu(Box::new(tray_menu.menu.clone()))
+        .with_menu_on_left_click(primary_click == ClickAction::OpenMenu)
+        .with_tooltip(tooltip_text(&state))
+        .with_icon(initial_icon)
+        .with_icon_as_template(initial_template)
         .build()?;
 
+    // Global shortcut for Enable/Disable, shown next to the toggle item
+    let accelerator = state.config.lock().unwrap().toggle_hotkey.clone();
+    let mut toggle_hotkey = register_toggle_hotkey(accelerator.as_deref(), &tray_menu, &mut notifications);
+
+    // Settings window, while open
+    let mut preferences: Option<PreferencesWindow> = None;
+
     // Clone state for event handling
     let state_clone = state.clone();
     let tray_icon_handle = Arc::new(Mutex::new(tray_icon));
@@ -451,47 +1060,166 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Menu event handler
     let menu_channel = MenuEvent::receiver();
+    let tray_channel = TrayIconEvent::receiver();
     
     // Timer for polling process status
     let mut last_check = std::time::Instant::now();
-    let mut last_running_state = state.is_lapsus_running();
+    // The last scan of lapsus_rust: from the poll, or after a start or stop
+    let mut last_daemon = daemon;
+    let mut last_tooltip = tooltip_text(&state);
     
     // Track current display for auto-restart on display switch
     #[cfg(target_os = "macos")]
     let mut last_display_id: Option<u32> = None;
 
-    event_loop.run(move |_event, _, control_flow| {
+    // Sleep/wake, screen lock and user switching
+    let mut session_monitor = session::platform_monitor();
+    let mut session_policy = {
+        let config = state.config.lock().unwrap();
+        SessionPolicyEngine::new(config.session_policy.clone())
+    };
+
+    // Battery and Low Power Mode
+    let mut power_source = power::platform_source();
+
+    // Watch the frontmost app for enable/disable rules
+    let mut foreground = ForegroundWatcher::new(foreground::platform_source());
+
+    event_loop.run(move |event, event_loop_target, control_flow| {
         *control_flow = tao::event_loop::ControlFlow::WaitUntil(
             std::time::Instant::now() + Duration::from_millis(100)
         );
 
-        // Check for menu events
-        if let Ok(event) = menu_channel.try_recv() {
-            let menu_id = event.id.0.as_str();
-            
+        // Closing the preferences window discards unsaved changes
+        if let Event::WindowEvent {
+            window_id,
+            event: WindowEvent::CloseRequested,
+            ..
+        } = event
+        {
+            if preferences.as_ref().is_some_and(|window| window.id() == window_id) {
+                preferences = None;
+            }
+        }
+
+        // Commands come from menu items, the command line, or from the
+        // tray icon for the configured primary click
+        let mut command = menu_channel.try_recv().ok().map(|event| event.id.0);
+
+        // From this launch's arguments, or handed over by a later launch
+        if command.is_none() {
+            command = launch_command
+                .take()
+                .or_else(|| instance.as_ref().and_then(|guard| guard.poll()))
+                .map(str::to_string);
+        }
+
+        // The global hotkey goes through the same Enable/Disable handler
+        if toggle_hotkey.as_ref().is_some_and(|hotkey| hotkey.pressed()) {
+            command = Some("toggle".to_string());
+        }
+
+        // Refresh the status header in place from the last scan when the
+        // pointer reaches the icon or it is clicked, i.e. right before the
+        // menu opens
+        let mut menu_opening = false;
+        while let Ok(event) = tray_channel.try_recv() {
+            match event {
+                TrayIconEvent::Click {
+                    button: MouseButton::Left,
+                    button_state: MouseButtonState::Up,
+                    ..
+                } => match primary_click.command() {
+                    Some(click_command) => command = Some(click_command.to_string()),
+                    None => menu_opening = true,
+                },
+                TrayIconEvent::Click { .. } | TrayIconEvent::Enter { .. } => menu_opening = true,
+                _ => {}
+            }
+        }
+        if menu_opening {
+            tray_menu.set_status(daemon::status_text(last_daemon.as_ref()));
+        }
+
+        if let Some(command) = command {
+            let mut menu_id = command.as_str();
+            if menu_id == "toggle" {
+                menu_id = tray_menu.toggle_command();
+            }
+            let _command = tracing::info_span!("command", id = menu_id).entered();
+            tracing::info!("Running {}", menu_id);
+
             match menu_id {
                 "enable" => {
-                    if let Err(e) = state_clone.start_lapsus() {
-                        show_error_dialog(&format!("Failed to start lapsus_rust: {}", e));
+                    state_clone.pause.lock().unwrap().cancel();
+                    state_clone.rules.lock().unwrap().reset();
+                    state_clone.power.lock().unwrap().reset();
+                    session_policy.reset();
+                    state_clone.schedule.lock().unwrap().manual_override();
+                    icon_display.begin(true, Instant::now());
+                    if let Err(e) = state_clone.start_lapsus(Source::User) {
+                        icon_display.fail(last_daemon.is_some());
+                        notifications.error(&t!("error-start", error = e));
                     } else {
-                        // Update menu and icon
-                        if let Ok(new_menu) = build_menu(&state_clone) {
-                            let tray = tray_clone.lock().unwrap();
-                            let _ = tray.set_menu(Some(Box::new(new_menu)));
-                            let _ = tray.set_icon(Some(state_clone.icon_enabled.clone()));
-                        }
+                        state_clone.remember_enabled(true);
+                        // Update menu; the icon animates until the daemon is up
+                        last_daemon = state_clone.daemon_info();
+                        let tray = tray_clone.lock().unwrap();
+                        refresh_tray(&state_clone, &tray, &mut tray_menu, last_daemon.as_ref());
                     }
                 }
                 "disable" => {
-                    if let Err(e) = state_clone.stop_lapsus() {
-                        show_error_dialog(&format!("Failed to stop lapsus_rust: {}", e));
+                    state_clone.pause.lock().unwrap().cancel();
+                    state_clone.rules.lock().unwrap().reset();
+                    state_clone.power.lock().unwrap().reset();
+                    session_policy.reset();
+                    state_clone.schedule.lock().unwrap().manual_override();
+                    icon_display.begin(false, Instant::now());
+                    if let Err(e) = state_clone.stop_lapsus(Source::User) {
+                        icon_display.fail(last_daemon.is_some());
+                        notifications.error(&t!("error-stop", error = e));
                     } else {
-                        // Update menu and icon
-                        if let Ok(new_menu) = build_menu(&state_clone) {
-                            let tray = tray_clone.lock().unwrap();
-                            let _ = tray.set_menu(Some(Box::new(new_menu)));
-                            let _ = tray.set_icon(Some(state_clone.icon_disabled.clone()));
-                        }
+                        state_clone.remember_enabled(false);
+                        // Update menu; the icon animates until the daemon is gone
+                        last_daemon = state_clone.daemon_info();
+                        let tray = tray_clone.lock().unwrap();
+                        refresh_tray(&state_clone, &tray, &mut tray_menu, last_daemon.as_ref());
+                    }
+                }
+                id if id.starts_with("pause_") => {
+                    let minutes = id["pause_".len()..].parse::<u64>().unwrap_or(PAUSE_MINUTES[0]);
+                    state_clone.rules.lock().unwrap().reset();
+                    state_clone.power.lock().unwrap().reset();
+                    session_policy.reset();
+                    state_clone.schedule.lock().unwrap().manual_override();
+
+                    icon_display.begin(false, Instant::now());
+                    if let Err(e) = state_clone.stop_lapsus(Source::Pause) {
+                        icon_display.fail(last_daemon.is_some());
+                        notifications.error(&t!("error-pause", error = e));
+                    } else {
+                        state_clone.pause.lock().unwrap()
+                            .start(Duration::from_secs(minutes * 60), SystemTime::now());
+                        last_daemon = state_clone.daemon_info();
+                        let tray = tray_clone.lock().unwrap();
+                        refresh_tray(&state_clone, &tray, &mut tray_menu, last_daemon.as_ref());
+                    }
+                }
+                "restart" => {
+                    icon_display.begin(true, Instant::now());
+                    let result = if last_daemon.is_some() {
+                        state_clone.restart_lapsus(Source::User)
+                    } else {
+                        state_clone.start_lapsus(Source::User)
+                    };
+                    if let Err(e) = result {
+                        icon_display.fail(last_daemon.is_some());
+                        notifications.error(&t!("error-restart", error = e));
+                    } else {
+                        state_clone.remember_enabled(true);
+                        last_daemon = state_clone.daemon_info();
+                        let tray = tray_clone.lock().unwrap();
+                        refresh_tray(&state_clone, &tray, &mut tray_menu, last_daemon.as_ref());
                     }
                 }
                 "start_at_login" => {
@@ -500,47 +1228,354 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     drop(config);
                     
                     if let Err(e) = state_clone.toggle_auto_launch(!current) {
-                        show_error_dialog(&format!("Failed to toggle auto-launch: {}", e));
+                        notifications.error(&t!("error-auto-launch", error = e));
+                    }
+                    // Also on failure, to undo the click's own toggle
+                    let tray = tray_clone.lock().unwrap();
+                    refresh_tray(&state_clone, &tray, &mut tray_menu, last_daemon.as_ref());
+                }
+                "preferences" => {
+                    if let Some(window) = &preferences {
+                        window.focus();
                     } else {
-                        // Update menu to reflect new state
-                        if let Ok(new_menu) = build_menu(&state_clone) {
-                            let tray = tray_clone.lock().unwrap();
-                            let _ = tray.set_menu(Some(Box::new(new_menu)));
+                        let form = state_clone.config.lock().unwrap().preferences_form();
+                        match PreferencesWindow::open(event_loop_target, &form) {
+                            Ok(window) => preferences = Some(window),
+                            Err(e) => notifications.error(&t!("error-open-preferences", error = e)),
+                        }
+                    }
+                }
+                id if id.starts_with("log_level_") => {
+                    if let Some(level) = LogLevel::from_id(&id["log_level_".len()..]) {
+                        logging.set_level(level);
+                        tracing::info!("Log level set to {}", level.id());
+                        state_clone.config.lock().unwrap().log_level = level;
+                        if let Err(e) = state_clone.save_config() {
+                            tracing::error!("Failed to save config: {}", e);
                         }
+                        let tray = tray_clone.lock().unwrap();
+                        refresh_tray(&state_clone, &tray, &mut tray_menu, last_daemon.as_ref());
                     }
                 }
                 "about" => {
                     show_about_dialog();
                 }
-                "quit" => {
-                    // Check if lapsus_rust is running and stop it
-                    if state_clone.is_lapsus_running() {
-                        let _ = state_clone.stop_lapsus();
+                "quit" | "quit_alternate" => {
+                    let mut quit_behavior = state_clone.config.lock().unwrap().quit_behavior;
+                    if menu_id == "quit_alternate" {
+                        quit_behavior = quit_behavior.other();
+                    }
+                    // Either way `last_enabled` stays what the user last
+                    // chose, e.g. not whatever a rule or the schedule left
+                    match quit_behavior {
+                        QuitBehavior::Stop => {
+                            if last_daemon.is_some() {
+                                if let Err(e) = state_clone.stop_lapsus(Source::Quit) {
+                                    tracing::error!("Failed to stop lapsus_rust on quit: {}", e);
+                                }
+                            }
+                        }
+                        QuitBehavior::LeaveRunning => {}
                     }
                     *control_flow = tao::event_loop::ControlFlow::Exit;
                 }
                 _ => {
-                    eprintln!("Unknown menu item: {}", menu_id);
+                    tracing::warn!("Unknown menu item: {}", menu_id);
+                }
+            }
+        }
+
+        // Requests from the preferences window
+        let request = preferences.as_ref().and_then(|window| window.poll());
+        match request {
+            Some(PreferencesRequest::Browse) => {
+                let current = state_clone.lapsus_path.lock().unwrap().clone();
+                if let (Some(path), Some(window)) = (preferences::pick_binary(&current), &preferences) {
+                    window.set_binary_path(&path);
+                }
+            }
+            Some(PreferencesRequest::Cancel) => preferences = None,
+            Some(PreferencesRequest::Save { form }) => {
+                let old = state_clone.config.lock().unwrap().clone();
+                let new = old.with_preferences(form);
+                if let Err(errors) = new.validate() {
+                    if let Some(window) = &preferences {
+                        window.show_errors(&errors);
+                    }
+                } else {
+                    *state_clone.config.lock().unwrap() = new.clone();
+                    let saved = if new.start_at_login != old.start_at_login {
+                        state_clone.toggle_auto_launch(new.start_at_login)
+                    } else {
+                        state_clone.save_config()
+                    };
+                    if let Err(e) = saved {
+                        notifications.error(&t!("error-save-preferences", error = e));
+                    }
+
+                    if new.lapsus_rust_path != old.lapsus_rust_path {
+                        if let Err(e) = state_clone.relocate_lapsus_rust() {
+                            notifications.error(&t!("error-lapsus-path", error = e));
+                        }
+                    }
+                    let daemon_changed = new.daemon_at_login != old.daemon_at_login;
+                    if daemon_changed
+                        || new.lapsus_rust_path != old.lapsus_rust_path
+                        || new.launch_args != old.launch_args
+                    {
+                        if let Err(e) = state_clone.update_daemon_login(daemon_changed) {
+                            notifications.error(&t!("error-daemon-login", error = e));
+                        }
+                        // It may have been moved under its LaunchAgent or unit
+                        last_daemon = state_clone.daemon_info();
+                    }
+                    if new.toggle_hotkey != old.toggle_hotkey {
+                        // Release the old shortcut first so it can be reused
+                        drop(toggle_hotkey.take());
+                        toggle_hotkey =
+                            register_toggle_hotkey(new.toggle_hotkey.as_deref(), &tray_menu, &mut notifications);
+                    }
+                    notifications.set_policy(new.notifications);
+
+                    preferences = None;
+                    let tray = tray_clone.lock().unwrap();
+                    refresh_tray(&state_clone, &tray, &mut tray_menu, last_daemon.as_ref());
+                }
+            }
+            None => {}
+        }
+
+        // Apply frontmost-app rules when the foreground app changes
+        if let Some(app) = foreground.poll() {
+            let decision = state_clone.rules.lock().unwrap().on_app_changed(&app, last_daemon.is_some());
+
+            if let Some(decision) = decision {
+                let acted = match state_clone.apply_decision(decision, Source::AppRule) {
+                    Ok(false) => false,
+                    Ok(true) => {
+                        notifications.state_change(&match decision {
+                            RuleDecision::Start => t!("state-rule-enabled", app = app.name),
+                            RuleDecision::Stop => t!("state-rule-disabled", app = app.name),
+                        });
+                        true
+                    }
+                    Err(e) => {
+                        notifications.error(&t!("error-rule", app = app.name, error = e));
+                        true
+                    }
+                };
+                if acted {
+                    last_daemon = state_clone.daemon_info();
                 }
             }
+
+            // Update even without a decision: the override label may have changed
+            let tray = tray_clone.lock().unwrap();
+            refresh_tray(&state_clone, &tray, &mut tray_menu, last_daemon.as_ref());
         }
 
         // Poll for process state changes every 2 seconds
         if last_check.elapsed() > Duration::from_secs(2) {
-            let is_running = state_clone.is_lapsus_running();
-            
-            if is_running != last_running_state {
-                // State changed, update UI
-                if let Ok(new_menu) = build_menu(&state_clone) {
-                    let tray = tray_clone.lock().unwrap();
-                    let _ = tray.set_menu(Some(Box::new(new_menu)));
-                    let _ = tray.set_icon(Some(state_clone.get_current_icon()));
+            let _poll = tracing::debug_span!("poll").entered();
+            let started = Instant::now();
+            let mut daemon = state_clone.daemon_info();
+            let mut is_running = daemon.is_some();
+
+            // Bring lapsus_rust back if it exited on its own
+            let action = state_clone.supervisor.lock().unwrap()
+                .on_poll(last_daemon.is_some(), is_running, Instant::now());
+            if action.is_some() {
+                state_clone.stats.lock().unwrap().record(EventKind::Crashed, Source::Supervisor);
+            }
+            match action {
+                Some(SupervisorAction::Restart) => {
+                    match state_clone.start_lapsus(Source::Supervisor) {
+                        Ok(()) => notifications.crash(&t!("crash-restarted")),
+                        Err(e) => notifications.crash(&t!("crash-restart-failed", error = e)),
+                    }
+                    daemon = state_clone.daemon_info();
+                    is_running = daemon.is_some();
+                }
+                Some(SupervisorAction::GiveUp) => {
+                    notifications.crash(&t!("crash-gave-up"));
+                }
+                None => {}
+            }
+
+            // Restart lapsus_rust if it's running but hung or leaking
+            let problem = state_clone.health.lock().unwrap().check(daemon.as_ref(), Instant::now());
+            if let Some(problem) = problem {
+                tracing::warn!(pid = daemon.as_ref().map(|d| d.pid), "Unhealthy: {}", problem);
+                let action = if state_clone.health.lock().unwrap().restarts() {
+                    Some(state_clone.supervisor.lock().unwrap().on_unhealthy(Instant::now()))
+                } else {
+                    None
+                };
+                match action {
+                    Some(SupervisorAction::Restart) => {
+                        match state_clone.restart_lapsus(Source::Health) {
+                            Ok(()) => notifications.crash(&t!("health-restarted", problem = problem)),
+                            Err(e) => notifications.crash(&t!("health-restart-failed", problem = problem, error = e)),
+                        }
+                        daemon = state_clone.daemon_info();
+                        is_running = daemon.is_some();
+                    }
+                    Some(SupervisorAction::GiveUp) => {
+                        notifications.crash(&t!("health-gave-up", problem = problem));
+                    }
+                    None => notifications.crash(&t!("health-unhealthy", problem = problem)),
                 }
-                last_running_state = is_running;
+            }
+            icon_display.set_degraded(state_clone.health.lock().unwrap().problem().is_some());
+
+            // React to wake, screen lock and user switching
+            for event in session_monitor.poll() {
+                if let Some(decision) = session_policy.on_event(event, is_running) {
+                    let result = match decision {
+                        SessionDecision::Start => state_clone.apply_decision(RuleDecision::Start, Source::Session),
+                        SessionDecision::Stop => state_clone.stop_lapsus(Source::Session).map(|()| true),
+                        SessionDecision::Restart => state_clone.restart_lapsus(Source::Session).map(|()| true),
+                    };
+                    let acted = result.unwrap_or_else(|e| {
+                        notifications.error(&t!("error-session", event = format!("{:?}", event), error = e));
+                        true
+                    });
+                    if acted {
+                        daemon = state_clone.daemon_info();
+                        is_running = daemon.is_some();
+                    }
+                }
+            }
+
+            // Apply schedule windows when a boundary is crossed
+            let decision = state_clone.schedule.lock().unwrap().evaluate(is_running);
+
+            if let Some(decision) = decision {
+                let acted = match state_clone.apply_decision(decision, Source::Schedule) {
+                    Ok(false) => false,
+                    Ok(true) => {
+                        notifications.state_change(&match decision {
+                            RuleDecision::Start => t!("state-schedule-enabled"),
+                            RuleDecision::Stop => t!("state-schedule-disabled"),
+                        });
+                        true
+                    }
+                    Err(e) => {
+                        notifications.error(&t!("error-schedule", error = e));
+                        true
+                    }
+                };
+                if acted {
+                    daemon = state_clone.daemon_info();
+                    is_running = daemon.is_some();
+                }
+            }
+
+            // Battery / Low Power Mode policy
+            if let Some(status) = power_source.read() {
+                let decision = state_clone.power.lock().unwrap().evaluate(&status, is_running);
+
+                if let Some(decision) = decision {
+                    let result = match decision {
+                        PowerDecision::Start => state_clone.apply_decision(RuleDecision::Start, Source::Power),
+                        PowerDecision::Stop => state_clone.stop_lapsus(Source::Power).map(|()| true),
+                        PowerDecision::Restart => state_clone.restart_lapsus(Source::Power).map(|()| true),
+                    };
+                    let acted = match result {
+                        Ok(acted) => {
+                            let label = state_clone.power.lock().unwrap().label();
+                            notifications.state_change(&label.unwrap_or_else(|| t!("state-power-lifted")));
+                            acted
+                        }
+                        Err(e) => {
+                            notifications.error(&t!("error-power", error = e));
+                            true
+                        }
+                    };
+                    if acted {
+                        daemon = state_clone.daemon_info();
+                        is_running = daemon.is_some();
+                    }
+                }
+            }
+
+            // Resume after a timed pause
+            let resume = state_clone.pause.lock().unwrap().take_expired(SystemTime::now());
+            if resume {
+                icon_display.begin(true, Instant::now());
+                match state_clone.start_lapsus(Source::Pause) {
+                    Ok(()) => notifications.state_change(&t!("state-pause-ended")),
+                    Err(e) => {
+                        icon_display.fail(is_running);
+                        notifications.error(&t!("error-resume", error = e));
+                    }
+                }
+                daemon = state_clone.daemon_info();
+                is_running = daemon.is_some();
+            }
+
+            // Keep Start at Login honest about the OS login item, asking
+            // off the event loop and storing the answer here
+            if let Some(receiver) = &login_check {
+                match receiver.try_recv() {
+                    Ok(changes) => {
+                        state_clone.apply_login_changes(changes);
+                        login_check = None;
+                    }
+                    Err(mpsc::TryRecvError::Disconnected) => login_check = None,
+                    Err(mpsc::TryRecvError::Empty) => {}
+                }
+            } else if last_login_check.elapsed() > login_item::RECONCILE_INTERVAL {
+                let (sender, receiver) = mpsc::channel();
+                let state = state_clone.clone();
+                let spawned = std::thread::Builder::new()
+                    .name("login-items".to_string())
+                    .spawn(move || {
+                        let _ = sender.send(state.reconcile_login_items());
+                    });
+                match spawned {
+                    Ok(_) => login_check = Some(receiver),
+                    Err(e) => tracing::warn!("Failed to check the login items: {}", e),
+                }
+                last_login_check = Instant::now();
+            }
+
+            // Update the menu in place from this scan; tooltip only when
+            // it changes. The icon follows below.
+            if let Err(e) = tray_menu.update(&menu_view(&state_clone, daemon.as_ref())) {
+                tracing::warn!("Failed to update the menu: {}", e);
+            }
+            let tooltip = tooltip_text(&state_clone);
+            if tooltip != last_tooltip {
+                if let Err(e) = tray_clone.lock().unwrap().set_tooltip(Some(&tooltip)) {
+                    tracing::warn!("Failed to set the tooltip: {}", e);
+                }
+                last_tooltip = tooltip;
+            }
+            last_daemon = daemon;
+
+            // Dark mode may have been switched, or the menu bar moved to a
+            // display with another scale factor, since the last poll
+            if let Some(appearance) = &appearance {
+                icon_display.set_dark(appearance.is_dark());
+            }
+            if let Some(scale) = tray_scale(&tray_clone.lock().unwrap(), event_loop_target) {
+                icon_scale = scale;
             }
             
+            let elapsed = started.elapsed();
+            tracing::trace!(running = is_running, ?elapsed, "Polled");
+            state_clone.metrics.lock().unwrap().observe_poll(last_daemon.as_ref(), elapsed);
+            state_clone.stats.lock().unwrap().heartbeat();
             last_check = std::time::Instant::now();
         }
+
+        // Icon for the current state, advancing any start/stop animation
+        let paused = state_clone.pause.lock().unwrap().is_active();
+        let icons = icon_cache.get(icon_scale);
+        if let Some((icon, template)) = icon_display.next(&icons, last_daemon.is_some(), paused, Instant::now()) {
+            show_icon(&tray_clone.lock().unwrap(), icon, template);
+        }
         
         // Check for display changes and auto-restart lapsus (macOS only)
         #[cfg(target_os = "macos")]
@@ -565,12 +1600,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 
                                 // Display changed?
                                 if let Some(last_id) = last_display_id {
-                                    if last_id != display_id && state_clone.is_lapsus_running() {
+                                    let restart = state_clone.config.lock().unwrap().display_switch
+                                        == DisplaySwitchAction::Restart;
+                                    if restart && last_id != display_id && last_daemon.is_some() {
                                         // Cursor moved to different display while lapsus is running
-                                        // Quick restart: disable and re-enable within ~50ms
-                                        let _ = state_clone.stop_lapsus();
-                                        std::thread::sleep(Duration::from_millis(50));
-                                        let _ = state_clone.start_lapsus();
+                                        let _display = tracing::info_span!("display_switch", from = last_id, to = display_id)
+                                            .entered();
+                                        tracing::info!("Restarting lapsus_rust for the new display");
+                                        if let Err(e) = state_clone.restart_lapsus(Source::DisplaySwitch) {
+                                            tracing::error!("Display-switch restart failed: {}", e);
+                                        }
                                     }
                                 }
                                 last_display_id = Some(display_id);
@@ -583,3 +1622,65 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json(config: &Config) -> serde_json::Value {
+        serde_json::to_value(config).unwrap()
+    }
+
+    #[test]
+    fn preferences_form_round_trips() {
+        let config = Config {
+            lapsus_rust_path: Some("/opt/lapsus/lapsus_rust".to_string()),
+            launch_args: vec!["--speed".to_string(), "2".to_string()],
+            start_at_login: true,
+            restore_state: false,
+            quit_behavior: QuitBehavior::LeaveRunning,
+            display_switch: DisplaySwitchAction::Nothing,
+            toggle_hotkey: Some("cmd+shift+l".to_string()),
+            notifications: NotificationPolicy {
+                state_changes: true,
+                ..NotificationPolicy::default()
+            },
+            metrics_port: Some(9464),
+            ..Config::default()
+        };
+        assert_eq!(json(&config.with_preferences(config.preferences_form())), json(&config));
+    }
+
+    #[test]
+    fn preferences_leave_other_settings_alone() {
+        let config = Config {
+            lapsus_rust_path: Some("/opt/lapsus/lapsus_rust".to_string()),
+            toggle_hotkey: Some("cmd+shift+l".to_string()),
+            last_enabled: Some(false),
+            metrics_port: Some(9464),
+            ..Config::default()
+        };
+        let form = PreferencesForm {
+            lapsus_rust_path: String::new(),
+            toggle_hotkey: String::new(),
+            daemon_at_login: true,
+            ..config.preferences_form()
+        };
+        let saved = config.with_preferences(form);
+
+        // Emptied fields mean "not set"
+        assert_eq!(saved.lapsus_rust_path, None);
+        assert_eq!(saved.toggle_hotkey, None);
+        assert!(saved.daemon_at_login);
+        assert_eq!(saved.last_enabled, Some(false));
+        assert_eq!(saved.metrics_port, Some(9464));
+    }
+
+    #[test]
+    fn missing_settings_take_the_defaults() {
+        let config: Config = serde_json::from_str("{}").unwrap();
+        assert_eq!(json(&config), json(&Config::default()));
+        assert!(config.restore_state);
+        assert!(!config.start_at_login);
+    }
+}
@@ -0,0 +1,247 @@
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::daemon::DaemonInfo;
+use crate::stats::{Event, EventKind, EventLog, Source};
+
+/// Upper bounds of the poll latency histogram, in seconds.
+const POLL_BUCKETS: [f64; 9] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+/// What the poll loop last saw, for `/metrics`.
+pub struct Metrics {
+    /// Unix time the app started; restart and crash counters count from here
+    started: i64,
+    daemon: Option<DaemonInfo>,
+    /// Polls no slower than each of `POLL_BUCKETS`, and in total
+    poll_buckets: [u64; POLL_BUCKETS.len()],
+    poll_count: u64,
+    poll_seconds: f64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() as i64);
+        Self {
+            started,
+            daemon: None,
+            poll_buckets: [0; POLL_BUCKETS.len()],
+            poll_count: 0,
+            poll_seconds: 0.0,
+        }
+    }
+
+    /// Records one poll: the daemon it found and how long it took.
+    pub fn observe_poll(&mut self, daemon: Option<&DaemonInfo>, elapsed: Duration) {
+        self.daemon = daemon.cloned();
+        let seconds = elapsed.as_secs_f64();
+        for (bucket, &bound) in self.poll_buckets.iter_mut().zip(&POLL_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.poll_count += 1;
+        self.poll_seconds += seconds;
+    }
+
+    /// Prometheus text format, with counters taken from `events`.
+    pub fn render(&self, events: &[Event]) -> String {
+        let mut text = String::new();
+        let stats = self.daemon.as_ref().and_then(|daemon| daemon.stats.as_ref());
+        let since_start = || events.iter().filter(|event| event.at >= self.started);
+
+        gauge(&mut text, "lapsus_daemon_up", "Whether lapsus_rust is running", self.daemon.is_some() as u8);
+        if let Some(stats) = stats {
+            gauge(
+                &mut text,
+                "lapsus_daemon_uptime_seconds",
+                "Time since lapsus_rust started",
+                stats.uptime.as_secs(),
+            );
+            gauge(
+                &mut text,
+                "lapsus_daemon_cpu_percent",
+                "CPU used by lapsus_rust, percent of one core",
+                stats.cpu_usage,
+            );
+            gauge(
+                &mut text,
+                "lapsus_daemon_resident_memory_bytes",
+                "Resident memory of lapsus_rust",
+                stats.memory,
+            );
+        }
+
+        header(&mut text, "lapsus_daemon_restarts_total", "Restarts of lapsus_rust by reason", "counter");
+        for source in Source::ALL {
+            let count = since_start().filter(|event| event.is_restart() && event.source == source).count();
+            let _ = writeln!(text, "lapsus_daemon_restarts_total{{reason=\"{}\"}} {}", source.id(), count);
+        }
+        header(&mut text, "lapsus_daemon_crashes_total", "Exits of lapsus_rust it wasn't asked for", "counter");
+        let crashes = since_start().filter(|event| event.kind == EventKind::Crashed).count();
+        let _ = writeln!(text, "lapsus_daemon_crashes_total {}", crashes);
+
+        let name = "lapsus_menubar_poll_duration_seconds";
+        header(&mut text, name, "Time to scan for lapsus_rust and apply policies", "histogram");
+        for (count, bound) in self.poll_buckets.iter().zip(POLL_BUCKETS) {
+            let _ = writeln!(text, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+        }
+        let _ = writeln!(text, "{}_bucket{{le=\"+Inf\"}} {}", name, self.poll_count);
+        let _ = writeln!(text, "{}_sum {}", name, self.poll_seconds);
+        let _ = writeln!(text, "{}_count {}", name, self.poll_count);
+        text
+    }
+}
+
+fn header(text: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(text, "# HELP {} {}", name, help);
+    let _ = writeln!(text, "# TYPE {} {}", name, kind);
+}
+
+fn gauge(text: &mut String, name: &str, help: &str, value: impl std::fmt::Display) {
+    header(text, name, help, "gauge");
+    let _ = writeln!(text, "{} {}", name, value);
+}
+
+/// Serves `GET /metrics` on `127.0.0.1:port` from a background thread.
+pub fn serve(
+    port: u16,
+    metrics: Arc<Mutex<Metrics>>,
+    events: Arc<Mutex<EventLog>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    tracing::info!("Serving metrics on http://{}/metrics", listener.local_addr()?);
+    std::thread::Builder::new().name("metrics".to_string()).spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = respond(stream, &metrics, &events) {
+                tracing::debug!("Metrics request failed: {}", e);
+            }
+        }
+    })?;
+    Ok(())
+}
+
+fn respond(
+    mut stream: TcpStream,
+    metrics: &Mutex<Metrics>,
+    events: &Mutex<EventLog>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Headers are not needed, but are read so the client sees a clean close
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut words = request.split_whitespace();
+    let (method, path) = (words.next().unwrap_or_default(), words.next().unwrap_or_default());
+    let (status, body) = match (method, path) {
+        ("GET", "/metrics") => {
+            let events = events.lock().unwrap().events().to_vec();
+            ("200 OK", metrics.lock().unwrap().render(&events))
+        }
+        ("GET", _) => ("404 Not Found", "Not found; try /metrics\n".to_string()),
+        _ => ("405 Method Not Allowed", String::new()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::ProcessStats;
+    use sysinfo::ProcessStatus;
+    use std::io::Read;
+
+    fn event(at: i64, kind: EventKind, source: Source) -> Event {
+        Event { at, kind, source }
+    }
+
+    #[test]
+    fn renders_daemon_state_and_counters() {
+        let mut metrics = Metrics::new();
+        let daemon = DaemonInfo {
+            pid: 42,
+            stats: Some(ProcessStats {
+                uptime: Duration::from_secs(90),
+                cpu_usage: 1.5,
+                memory: 4096,
+                status: ProcessStatus::Sleep,
+            }),
+        };
+        metrics.observe_poll(Some(&daemon), Duration::from_millis(20));
+        metrics.observe_poll(Some(&daemon), Duration::from_millis(300));
+
+        let now = metrics.started;
+        let events = [
+            // Before the app started: not counted
+            event(now - 10, EventKind::Restarted, Source::DisplaySwitch),
+            event(now, EventKind::Restarted, Source::DisplaySwitch),
+            event(now + 1, EventKind::Crashed, Source::Supervisor),
+            event(now + 1, EventKind::Enabled, Source::Supervisor),
+        ];
+        let text = metrics.render(&events);
+        for line in [
+            "lapsus_daemon_up 1",
+            "lapsus_daemon_uptime_seconds 90",
+            "lapsus_daemon_cpu_percent 1.5",
+            "lapsus_daemon_resident_memory_bytes 4096",
+            "lapsus_daemon_restarts_total{reason=\"display_switch\"} 1",
+            "lapsus_daemon_restarts_total{reason=\"supervisor\"} 1",
+            "lapsus_daemon_restarts_total{reason=\"user\"} 0",
+            "lapsus_daemon_crashes_total 1",
+            "lapsus_menubar_poll_duration_seconds_bucket{le=\"0.025\"} 1",
+            "lapsus_menubar_poll_duration_seconds_bucket{le=\"0.5\"} 2",
+            "lapsus_menubar_poll_duration_seconds_bucket{le=\"+Inf\"} 2",
+            "lapsus_menubar_poll_duration_seconds_count 2",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {:?} in\n{}", line, text);
+        }
+    }
+
+    #[test]
+    fn leaves_out_stats_of_a_stopped_daemon() {
+        let mut metrics = Metrics::new();
+        metrics.observe_poll(None, Duration::from_millis(5));
+        let text = metrics.render(&[]);
+        assert!(text.contains("lapsus_daemon_up 0\n"));
+        assert!(!text.contains("lapsus_daemon_uptime_seconds"));
+    }
+
+    #[test]
+    fn serves_metrics_over_http() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        let metrics = Mutex::new(Metrics::new());
+        let events = Mutex::new(EventLog::in_memory());
+
+        let get = |path: &str| {
+            let mut client = TcpStream::connect(address).unwrap();
+            write!(client, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            respond(stream, &metrics, &events).unwrap();
+            let mut response = String::new();
+            client.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let response = get("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\r\n\r\n# HELP lapsus_daemon_up"));
+        assert!(get("/").starts_with("HTTP/1.1 404"));
+    }
+}
@@ -0,0 +1,312 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::i18n::t;
+
+/// At most this many notifications per `BURST_WINDOW`...
+const BURST: usize = 3;
+const BURST_WINDOW: Duration = Duration::from_secs(60);
+/// ...and the same message at most once per `REPEAT_WINDOW`.
+const REPEAT_WINDOW: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Normal,
+    Critical,
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+    pub urgency: Urgency,
+}
+
+/// Platform hook for showing a non-modal notification.
+pub trait Notifier {
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// Notification Center, via the app's bundle identifier.
+#[cfg(target_os = "macos")]
+pub struct MacNotifier;
+
+#[cfg(target_os = "macos")]
+impl MacNotifier {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        mac_notification_sys::set_application("com.lapsus.menubar")?;
+        Ok(Self)
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Notifier for MacNotifier {
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn std::error::Error>> {
+        // Delivery can block until Notification Center answers; keep it off
+        // the event loop
+        let notification = notification.clone();
+        std::thread::spawn(move || {
+            let mut options = mac_notification_sys::Notification::new();
+            if notification.urgency == Urgency::Critical {
+                options.sound("Basso");
+            }
+            if let Err(e) = mac_notification_sys::send_notification(
+                &notification.title,
+                None,
+                &notification.body,
+                Some(&options),
+            ) {
+                tracing::warn!("{}: {} (notification failed: {})", notification.title, notification.body, e);
+            }
+        });
+        Ok(())
+    }
+}
+
+/// `org.freedesktop.Notifications` on the session bus. Calls go through a
+/// worker thread, so a stalled notification daemon can't freeze the tray.
+#[cfg(target_os = "linux")]
+pub struct FreedesktopNotifier {
+    sender: std::sync::mpsc::Sender<Notification>,
+}
+
+#[cfg(target_os = "linux")]
+impl FreedesktopNotifier {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let connection = zbus::blocking::Connection::session()?;
+        let (sender, receiver) = std::sync::mpsc::channel::<Notification>();
+        std::thread::Builder::new().name("notifications".to_string()).spawn(move || {
+            for notification in receiver {
+                if let Err(e) = Self::send(&connection, &notification) {
+                    tracing::warn!("{}: {} (notification failed: {})", notification.title, notification.body, e);
+                }
+            }
+        })?;
+        Ok(Self { sender })
+    }
+
+    fn send(connection: &zbus::blocking::Connection, notification: &Notification) -> Result<(), zbus::Error> {
+        use zbus::zvariant::Value;
+
+        // Urgency levels from the spec: 0 low, 1 normal, 2 critical
+        let urgency: u8 = match notification.urgency {
+            Urgency::Normal => 1,
+            Urgency::Critical => 2,
+        };
+        let mut hints: HashMap<&str, Value> = HashMap::new();
+        hints.insert("urgency", Value::U8(urgency));
+
+        connection.call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "Notify",
+            &(
+                crate::APP_NAME,
+                0u32, // replaces_id
+                "",   // app_icon
+                notification.title.as_str(),
+                notification.body.as_str(),
+                Vec::<&str>::new(), // actions
+                hints,
+                -1i32, // server default timeout
+            ),
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Notifier for FreedesktopNotifier {
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn std::error::Error>> {
+        self.sender
+            .send(notification.clone())
+            .map_err(|_| "the notification thread has stopped")?;
+        Ok(())
+    }
+}
+
+/// Fallback when no notification service is available.
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn std::error::Error>> {
+        tracing::info!("{}: {}", notification.title, notification.body);
+        Ok(())
+    }
+}
+
+pub fn platform_notifier() -> Box<dyn Notifier> {
+    #[cfg(target_os = "macos")]
+    let native = MacNotifier::new().map(|n| Box::new(n) as Box<dyn Notifier>);
+
+    #[cfg(target_os = "linux")]
+    let native = FreedesktopNotifier::new().map(|n| Box::new(n) as Box<dyn Notifier>);
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    let native: Result<Box<dyn Notifier>, Box<dyn std::error::Error>> = Err("no notification service".into());
+
+    native.unwrap_or_else(|e| {
+        tracing::warn!("Notifications unavailable, logging instead: {}", e);
+        Box::new(LogNotifier)
+    })
+}
+
+/// Keeps a crash loop or a flapping policy from flooding the screen.
+#[derive(Default)]
+pub struct RateLimiter {
+    sent: VecDeque<Instant>,
+    last_sent: HashMap<String, Instant>,
+    suppressed: usize,
+}
+
+impl RateLimiter {
+    /// Records and allows the message unless it is a recent repeat or the
+    /// burst budget is spent.
+    pub fn allow(&mut self, message: &str, now: Instant) -> bool {
+        while self.sent.front().is_some_and(|&at| now.duration_since(at) >= BURST_WINDOW) {
+            self.sent.pop_front();
+        }
+        self.last_sent.retain(|_, &mut at| now.duration_since(at) < REPEAT_WINDOW);
+
+        if self.sent.len() >= BURST || self.last_sent.contains_key(message) {
+            self.suppressed += 1;
+            return false;
+        }
+        self.sent.push_back(now);
+        self.last_sent.insert(message.to_string(), now);
+        true
+    }
+
+    /// How many messages were dropped since the last call.
+    pub fn take_suppressed(&mut self) -> usize {
+        std::mem::take(&mut self.suppressed)
+    }
+}
+
+/// Which notifications to show, stored in the config as `notifications`.
+/// Everything is logged regardless.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationPolicy {
+    /// Failed starts, stops and restarts
+    pub errors: bool,
+    /// lapsus_rust exiting unexpectedly
+    pub crashes: bool,
+    /// Schedules, rules, power policy and pauses switching lapsus_rust
+    pub state_changes: bool,
+}
+
+impl Default for NotificationPolicy {
+    fn default() -> Self {
+        Self {
+            errors: true,
+            crashes: true,
+            state_changes: false,
+        }
+    }
+}
+
+/// The app's notification front end: policy, rate limit, then backend.
+pub struct Notifications {
+    backend: Box<dyn Notifier>,
+    limiter: RateLimiter,
+    policy: NotificationPolicy,
+}
+
+impl Notifications {
+    pub fn new(policy: NotificationPolicy) -> Self {
+        Self {
+            backend: platform_notifier(),
+            limiter: RateLimiter::default(),
+            policy,
+        }
+    }
+
+    pub fn set_policy(&mut self, policy: NotificationPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn error(&mut self, body: &str) {
+        tracing::error!("{}", body);
+        if self.policy.errors {
+            self.send(&t!("notify-error-title"), body, Urgency::Critical);
+        }
+    }
+
+    pub fn crash(&mut self, body: &str) {
+        tracing::error!("{}", body);
+        if self.policy.crashes {
+            self.send(&t!("notify-crash-title"), body, Urgency::Critical);
+        }
+    }
+
+    pub fn state_change(&mut self, body: &str) {
+        tracing::info!("{}", body);
+        if self.policy.state_changes {
+            self.send(crate::APP_NAME, body, Urgency::Normal);
+        }
+    }
+
+    fn send(&mut self, title: &str, body: &str, urgency: Urgency) {
+        if !self.limiter.allow(body, Instant::now()) {
+            return;
+        }
+
+        let suppressed = self.limiter.take_suppressed();
+        let body = if suppressed > 0 {
+            t!("notify-suppressed", body = body, count = suppressed)
+        } else {
+            body.to_string()
+        };
+
+        let notification = Notification {
+            title: title.to_string(),
+            body,
+            urgency,
+        };
+        if let Err(e) = self.backend.notify(&notification) {
+            tracing::warn!("Notification failed: {}", e);
+            let _ = LogNotifier.notify(&notification);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_a_burst_of_three_a_minute() {
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(limiter.allow("a", at(0)));
+        assert!(limiter.allow("b", at(1)));
+        assert!(limiter.allow("c", at(2)));
+        assert!(!limiter.allow("d", at(3)));
+        assert!(!limiter.allow("e", at(59)));
+        assert_eq!(limiter.take_suppressed(), 2);
+        assert_eq!(limiter.take_suppressed(), 0);
+
+        // The first one has left the window
+        assert!(limiter.allow("f", at(60)));
+        assert!(!limiter.allow("g", at(60)));
+        assert!(limiter.allow("g", at(61)));
+    }
+
+    #[test]
+    fn suppresses_repeats_for_five_minutes() {
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(limiter.allow("crashed", at(0)));
+        assert!(!limiter.allow("crashed", at(120)));
+        assert!(!limiter.allow("crashed", at(299)));
+        assert!(limiter.allow("crashed", at(300)));
+        assert_eq!(limiter.take_suppressed(), 2);
+    }
+}
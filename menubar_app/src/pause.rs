@@ -0,0 +1,109 @@
+use std::time::{Duration, SystemTime};
+
+use crate::i18n::t;
+
+/// Pause lengths offered in the "Pause for" submenu, in minutes.
+pub const PAUSE_MINUTES: [u64; 3] = [5, 15, 60];
+
+/// A timed disable. Deadlines are wall-clock (`SystemTime`) rather than
+/// `Instant`, which stops advancing while the Mac sleeps, so a pause that
+/// ran out during sleep resumes right after wake.
+#[derive(Debug, Default)]
+pub struct Pause {
+    until: Option<SystemTime>,
+}
+
+impl Pause {
+    pub fn start(&mut self, length: Duration, now: SystemTime) {
+        self.until = Some(now + length);
+    }
+
+    pub fn cancel(&mut self) {
+        self.until = None;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.until.is_some()
+    }
+
+    pub fn remaining(&self, now: SystemTime) -> Option<Duration> {
+        let until = self.until?;
+        Some(until.duration_since(now).unwrap_or(Duration::ZERO))
+    }
+
+    /// Ends the pause and returns true once its deadline has passed.
+    pub fn take_expired(&mut self, now: SystemTime) -> bool {
+        match self.until {
+            Some(until) if now >= until => {
+                self.until = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Countdown text, e.g. "Paused, resumes in 12 min".
+    pub fn label(&self, now: SystemTime) -> Option<String> {
+        let remaining = self.remaining(now)?;
+        // Round up so the countdown never shows "0 min" while still paused
+        let minutes = remaining.as_secs().div_ceil(60).max(1);
+        Some(t!("pause-label", minutes = minutes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minutes(n: u64) -> Duration {
+        Duration::from_secs(n * 60)
+    }
+
+    #[test]
+    fn expires_once_at_its_deadline() {
+        let now = SystemTime::now();
+        let mut pause = Pause::default();
+        assert!(!pause.take_expired(now));
+
+        pause.start(minutes(5), now);
+        assert!(pause.is_active());
+        assert!(!pause.take_expired(now + minutes(5) - Duration::from_secs(1)));
+        assert!(pause.take_expired(now + minutes(5)));
+        assert!(!pause.is_active());
+        assert!(!pause.take_expired(now + minutes(6)));
+    }
+
+    #[test]
+    fn a_deadline_passed_during_sleep_expires_on_wake() {
+        let now = SystemTime::now();
+        let mut pause = Pause::default();
+        pause.start(minutes(15), now);
+        assert_eq!(pause.remaining(now + minutes(90)), Some(Duration::ZERO));
+        assert!(pause.take_expired(now + minutes(90)));
+    }
+
+    #[test]
+    fn cancel_ends_the_pause() {
+        let now = SystemTime::now();
+        let mut pause = Pause::default();
+        pause.start(minutes(60), now);
+        pause.cancel();
+        assert!(!pause.is_active());
+        assert_eq!(pause.label(now), None);
+        assert!(!pause.take_expired(now + minutes(60)));
+    }
+
+    #[test]
+    fn label_rounds_up_to_whole_minutes() {
+        let now = SystemTime::now();
+        let mut pause = Pause::default();
+        pause.start(minutes(15), now);
+        let label = |at| pause.label(at).unwrap();
+        assert_eq!(label(now), "Paused, resumes in 15 min");
+        assert_eq!(label(now + Duration::from_secs(1)), t!("pause-label", minutes = 15));
+        assert_eq!(label(now + Duration::from_secs(60)), t!("pause-label", minutes = 14));
+        assert_eq!(label(now + minutes(15) - Duration::from_secs(1)), t!("pause-label", minutes = 1));
+        // Never "0 min" while still paused
+        assert_eq!(label(now + minutes(20)), t!("pause-label", minutes = 1));
+    }
+}
@@ -0,0 +1,454 @@
+use serde::{Deserialize, Serialize};
+#[cfg(target_os = "linux")]
+use std::path::{Path, PathBuf};
+
+use crate::i18n::t;
+
+/// Snapshot of the machine's power situation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PowerStatus {
+    pub on_battery: bool,
+    pub battery_percent: Option<u8>,
+    pub low_power_mode: bool,
+}
+
+/// Platform hook for reading the power source.
+pub trait PowerSource {
+    fn read(&mut self) -> Option<PowerStatus>;
+}
+
+/// What to do with lapsus_rust while power is constrained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerAction {
+    Disable,
+    /// Restart lapsus_rust with `light_profile_args`
+    LightProfile,
+}
+
+/// Stored in the config as `power_policy`. Inactive unless
+/// `battery_threshold` is set or `on_low_power_mode` is true.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerPolicy {
+    /// Act when running on battery at or below this percentage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub battery_threshold: Option<u8>,
+    /// Act whenever Low Power Mode is on
+    pub on_low_power_mode: bool,
+    pub action: PowerAction,
+    /// Extra arguments for lapsus_rust in the light profile. While they
+    /// apply, lapsus_rust is started directly rather than through its
+    /// LaunchAgent or systemd unit, whose arguments are fixed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub light_profile_args: Vec<String>,
+}
+
+impl Default for PowerPolicy {
+    fn default() -> Self {
+        Self {
+            battery_threshold: None,
+            on_low_power_mode: false,
+            action: PowerAction::Disable,
+            light_profile_args: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerDecision {
+    Start,
+    Stop,
+    Restart,
+}
+
+/// Applies a `PowerPolicy` as the power status changes.
+pub struct PowerPolicyEngine {
+    policy: PowerPolicy,
+    /// Why power is constrained right now, e.g. "battery at 18%"
+    reason: Option<String>,
+    stopped: bool,
+}
+
+impl PowerPolicyEngine {
+    pub fn new(policy: PowerPolicy) -> Self {
+        Self {
+            policy,
+            reason: None,
+            stopped: false,
+        }
+    }
+
+    fn constraint(&self, status: &PowerStatus) -> Option<String> {
+        if self.policy.on_low_power_mode && status.low_power_mode {
+            return Some(t!("power-low-power-mode"));
+        }
+        let threshold = self.policy.battery_threshold?;
+        let percent = status.battery_percent?;
+        (status.on_battery && percent <= threshold).then(|| t!("power-battery", percent = percent))
+    }
+
+    pub fn evaluate(&mut self, status: &PowerStatus, running: bool) -> Option<PowerDecision> {
+        let reason = self.constraint(status);
+        let was_constrained = self.reason.is_some();
+        let constrained = reason.is_some();
+        self.reason = reason;
+
+        match (was_constrained, constrained, self.policy.action) {
+            (false, true, PowerAction::Disable) if running => {
+                self.stopped = true;
+                Some(PowerDecision::Stop)
+            }
+            (true, false, PowerAction::Disable) if std::mem::take(&mut self.stopped) && !running => {
+                Some(PowerDecision::Start)
+            }
+            (false, true, PowerAction::LightProfile) | (true, false, PowerAction::LightProfile)
+                if running =>
+            {
+                Some(PowerDecision::Restart)
+            }
+            _ => None,
+        }
+    }
+
+    /// Forget that we stopped the daemon, e.g. after a manual toggle.
+    pub fn reset(&mut self) {
+        self.stopped = false;
+    }
+
+    /// True while the policy wants lapsus_rust off.
+    pub fn holds_disabled(&self) -> bool {
+        self.reason.is_some() && self.policy.action == PowerAction::Disable
+    }
+
+    /// Extra launch arguments while the light profile is in effect.
+    pub fn profile_args(&self) -> &[String] {
+        if self.reason.is_some() && self.policy.action == PowerAction::LightProfile {
+            &self.policy.light_profile_args
+        } else {
+            &[]
+        }
+    }
+
+    /// Tooltip text for the current decision, e.g. "Disabled: battery at 18%".
+    pub fn label(&self) -> Option<String> {
+        let reason = self.reason.as_ref()?;
+        Some(match self.policy.action {
+            PowerAction::Disable => t!("power-disabled", reason = reason),
+            PowerAction::LightProfile => t!("power-light-profile", reason = reason),
+        })
+    }
+}
+
+/// Reads IOKit power sources and NSProcessInfo's Low Power Mode flag.
+#[cfg(target_os = "macos")]
+pub struct IoKitPowerSource;
+
+#[cfg(target_os = "macos")]
+impl PowerSource for IoKitPowerSource {
+    fn read(&mut self) -> Option<PowerStatus> {
+        use core_foundation::array::{CFArray, CFArrayRef};
+        use core_foundation::base::{CFType, CFTypeRef, TCFType};
+        use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+        use core_foundation::number::CFNumber;
+        use core_foundation::string::{CFString, CFStringRef};
+
+        #[link(name = "IOKit", kind = "framework")]
+        extern "C" {
+            fn IOPSCopyPowerSourcesInfo() -> CFTypeRef;
+            fn IOPSCopyPowerSourcesList(blob: CFTypeRef) -> CFArrayRef;
+            fn IOPSGetPowerSourceDescription(blob: CFTypeRef, ps: CFTypeRef) -> CFDictionaryRef;
+            fn IOPSGetProvidingPowerSourceType(snapshot: CFTypeRef) -> CFStringRef;
+        }
+
+        unsafe {
+            let blob_ref = IOPSCopyPowerSourcesInfo();
+            if blob_ref.is_null() {
+                return None;
+            }
+            let blob = CFType::wrap_under_create_rule(blob_ref);
+
+            let providing = IOPSGetProvidingPowerSourceType(blob.as_CFTypeRef());
+            let on_battery = !providing.is_null()
+                && CFString::wrap_under_get_rule(providing).to_string() == "Battery Power";
+
+            let mut battery_percent = None;
+            let list_ref = IOPSCopyPowerSourcesList(blob.as_CFTypeRef());
+            if !list_ref.is_null() {
+                let list: CFArray<CFType> = CFArray::wrap_under_create_rule(list_ref);
+                for source in list.iter() {
+                    let desc_ref = IOPSGetPowerSourceDescription(blob.as_CFTypeRef(), source.as_CFTypeRef());
+                    if desc_ref.is_null() {
+                        continue;
+                    }
+                    let desc: CFDictionary<CFString, CFType> = CFDictionary::wrap_under_get_rule(desc_ref);
+                    let number = |key: &str| {
+                        desc.find(CFString::new(key))
+                            .and_then(|value| value.downcast::<CFNumber>())
+                            .and_then(|n| n.to_i64())
+                    };
+                    if let (Some(current), Some(max)) = (number("Current Capacity"), number("Max Capacity")) {
+                        if max > 0 {
+                            battery_percent = Some((current * 100 / max).clamp(0, 100) as u8);
+                            break;
+                        }
+                    }
+                }
+            }
+
+            Some(PowerStatus {
+                on_battery,
+                battery_percent,
+                low_power_mode: low_power_mode_enabled(),
+            })
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn low_power_mode_enabled() -> bool {
+    use cocoa::base::{id, BOOL, NO, YES};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    unsafe {
+        let info: id = msg_send![class!(NSProcessInfo), processInfo];
+        // isLowPowerModeEnabled only exists on macOS 12+
+        let supported: BOOL = msg_send![info, respondsToSelector: sel!(isLowPowerModeEnabled)];
+        if supported == NO {
+            return false;
+        }
+        let enabled: BOOL = msg_send![info, isLowPowerModeEnabled];
+        enabled == YES
+    }
+}
+
+/// Reads `/sys/class/power_supply`, plus the ACPI platform profile as the
+/// closest thing Linux has to Low Power Mode.
+#[cfg(target_os = "linux")]
+pub struct SysfsPowerSource {
+    supply_dir: PathBuf,
+    platform_profile: PathBuf,
+}
+
+#[cfg(target_os = "linux")]
+impl SysfsPowerSource {
+    pub fn new() -> Self {
+        Self {
+            supply_dir: PathBuf::from("/sys/class/power_supply"),
+            platform_profile: PathBuf::from("/sys/firmware/acpi/platform_profile"),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_attr(dir: &Path, name: &str) -> Option<String> {
+    std::fs::read_to_string(dir.join(name)).ok().map(|s| s.trim().to_string())
+}
+
+#[cfg(target_os = "linux")]
+impl PowerSource for SysfsPowerSource {
+    fn read(&mut self) -> Option<PowerStatus> {
+        let mut ac_online = false;
+        let mut discharging = false;
+        let mut battery_percent = None;
+
+        for entry in std::fs::read_dir(&self.supply_dir).ok()?.flatten() {
+            let dir = entry.path();
+            match read_attr(&dir, "type").as_deref() {
+                Some("Mains") | Some("USB") => {
+                    ac_online |= read_attr(&dir, "online").as_deref() == Some("1");
+                }
+                // Skip peripheral batteries (mice, keyboards), which report scope=Device
+                Some("Battery") if read_attr(&dir, "scope").as_deref() != Some("Device") => {
+                    discharging |= read_attr(&dir, "status").as_deref() == Some("Discharging");
+                    if battery_percent.is_none() {
+                        battery_percent = read_attr(&dir, "capacity").and_then(|c| c.parse().ok());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Some(PowerStatus {
+            on_battery: battery_percent.is_some() && (discharging || !ac_online),
+            battery_percent,
+            low_power_mode: std::fs::read_to_string(&self.platform_profile)
+                .is_ok_and(|profile| profile.trim() == "low-power"),
+        })
+    }
+}
+
+/// Source for platforms without power information.
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub struct NoPowerSource;
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+impl PowerSource for NoPowerSource {
+    fn read(&mut self) -> Option<PowerStatus> {
+        None
+    }
+}
+
+pub fn platform_source() -> Box<dyn PowerSource> {
+    #[cfg(target_os = "macos")]
+    {
+        Box::new(IoKitPowerSource)
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        Box::new(SysfsPowerSource::new())
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        Box::new(NoPowerSource)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AC: PowerStatus = PowerStatus {
+        on_battery: false,
+        battery_percent: Some(100),
+        low_power_mode: false,
+    };
+
+    fn battery(percent: u8) -> PowerStatus {
+        PowerStatus {
+            on_battery: true,
+            battery_percent: Some(percent),
+            low_power_mode: false,
+        }
+    }
+
+    fn low_power(on_battery: bool) -> PowerStatus {
+        PowerStatus {
+            on_battery,
+            battery_percent: Some(80),
+            low_power_mode: true,
+        }
+    }
+
+    /// Feeds every reading to `engine`, with lapsus_rust following its
+    /// decisions, and returns them.
+    fn run(engine: &mut PowerPolicyEngine, readings: &[PowerStatus], mut running: bool) -> Vec<Option<PowerDecision>> {
+        readings
+            .iter()
+            .map(|status| {
+                let decision = engine.evaluate(status, running);
+                match decision {
+                    Some(PowerDecision::Start) => running = true,
+                    Some(PowerDecision::Stop) => running = false,
+                    _ => {}
+                }
+                decision
+            })
+            .collect()
+    }
+
+    fn threshold(percent: u8) -> PowerPolicy {
+        PowerPolicy {
+            battery_threshold: Some(percent),
+            ..PowerPolicy::default()
+        }
+    }
+
+    #[test]
+    fn stops_at_the_battery_threshold_and_restarts_on_ac() {
+        let mut engine = PowerPolicyEngine::new(threshold(20));
+        let decisions = run(&mut engine, &[AC, battery(30), battery(20)], true);
+        assert_eq!(decisions, [None, None, Some(PowerDecision::Stop)]);
+        assert!(engine.holds_disabled());
+        assert_eq!(engine.label().as_deref(), Some("Disabled: battery at 20%"));
+
+        let decisions = run(&mut engine, &[battery(15), AC], false);
+        assert_eq!(decisions, [None, Some(PowerDecision::Start)]);
+        assert!(!engine.holds_disabled());
+        assert_eq!(engine.label(), None);
+    }
+
+    #[test]
+    fn leaves_alone_what_it_did_not_stop() {
+        // Already off when the battery ran low: not started on AC
+        let mut engine = PowerPolicyEngine::new(threshold(20));
+        assert_eq!(run(&mut engine, &[battery(10), AC], false), [None, None]);
+
+        // Toggled by hand in between: the user's choice stands
+        let mut engine = PowerPolicyEngine::new(threshold(20));
+        assert_eq!(run(&mut engine, &[battery(10)], true), [Some(PowerDecision::Stop)]);
+        engine.reset();
+        assert_eq!(run(&mut engine, &[AC], false), [None]);
+
+        // A battery level on AC power doesn't count
+        let mut engine = PowerPolicyEngine::new(threshold(20));
+        let charging = PowerStatus {
+            on_battery: false,
+            battery_percent: Some(5),
+            low_power_mode: false,
+        };
+        assert_eq!(run(&mut engine, &[charging], true), [None]);
+    }
+
+    #[test]
+    fn low_power_mode_switches_to_the_light_profile() {
+        let mut engine = PowerPolicyEngine::new(PowerPolicy {
+            on_low_power_mode: true,
+            action: PowerAction::LightProfile,
+            light_profile_args: vec!["--fps".to_string(), "30".to_string()],
+            ..PowerPolicy::default()
+        });
+        assert!(engine.profile_args().is_empty());
+
+        assert_eq!(run(&mut engine, &[low_power(false)], true), [Some(PowerDecision::Restart)]);
+        assert_eq!(engine.profile_args(), ["--fps", "30"]);
+        assert!(!engine.holds_disabled());
+        assert_eq!(engine.label().as_deref(), Some("Light profile: Low Power Mode"));
+
+        assert_eq!(run(&mut engine, &[low_power(true), AC], true), [None, Some(PowerDecision::Restart)]);
+        assert!(engine.profile_args().is_empty());
+
+        // Nothing to restart while stopped; the arguments still apply at the next start
+        assert_eq!(run(&mut engine, &[low_power(false)], false), [None]);
+        assert_eq!(engine.profile_args(), ["--fps", "30"]);
+    }
+
+    #[test]
+    fn inactive_without_a_threshold_or_low_power_mode() {
+        let mut engine = PowerPolicyEngine::new(PowerPolicy::default());
+        assert_eq!(run(&mut engine, &[battery(1), low_power(true), AC], true), [None, None, None]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reads_sysfs_power_supplies() {
+        let root = std::env::temp_dir().join(format!("lapsus-power-{}", std::process::id()));
+        let supply = |name: &str, attrs: &[(&str, &str)]| {
+            let dir = root.join("power_supply").join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            for (attr, value) in attrs {
+                std::fs::write(dir.join(attr), format!("{}\n", value)).unwrap();
+            }
+        };
+        supply("AC", &[("type", "Mains"), ("online", "0")]);
+        supply("BAT0", &[("type", "Battery"), ("status", "Discharging"), ("capacity", "42")]);
+        supply("hidpp_battery_0", &[("type", "Battery"), ("scope", "Device"), ("capacity", "5")]);
+        std::fs::write(root.join("platform_profile"), "low-power\n").unwrap();
+
+        let mut source = SysfsPowerSource {
+            supply_dir: root.join("power_supply"),
+            platform_profile: root.join("platform_profile"),
+        };
+        assert_eq!(
+            source.read(),
+            Some(PowerStatus {
+                on_battery: true,
+                battery_percent: Some(42),
+                low_power_mode: true,
+            })
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use tao::dpi::LogicalSize;
+use tao::event_loop::EventLoopWindowTarget;
+use tao::window::{Window, WindowBuilder, WindowId};
+use wry::{WebView, WebViewBuilder};
+
+use crate::i18n::{self, t};
+use crate::notify::NotificationPolicy;
+use crate::{DisplaySwitchAction, QuitBehavior};
+
+const PAGE: &str = include_str!("preferences.html");
+
+/// The settings the window edits; everything else in the config is kept
+/// as it is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreferencesForm {
+    /// Empty to search the usual locations
+    pub lapsus_rust_path: String,
+    pub launch_args: Vec<String>,
+    /// The menu bar app's login item
+    pub start_at_login: bool,
+    pub daemon_at_login: bool,
+    pub restore_state: bool,
+    pub quit_behavior: QuitBehavior,
+    pub display_switch: DisplaySwitchAction,
+    /// Empty for no hotkey
+    pub toggle_hotkey: String,
+    pub notifications: NotificationPolicy,
+}
+
+/// Messages posted by the page.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PreferencesRequest {
+    Save { form: PreferencesForm },
+    Browse,
+    Cancel,
+}
+
+/// The settings window: a small HTML form in a webview.
+pub struct PreferencesWindow {
+    window: Window,
+    webview: WebView,
+    requests: Receiver<PreferencesRequest>,
+}
+
+impl PreferencesWindow {
+    pub fn open(
+        target: &EventLoopWindowTarget<()>,
+        form: &PreferencesForm,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let window = WindowBuilder::new()
+            .with_title(t!("prefs-title", app = crate::APP_NAME))
+            .with_inner_size(LogicalSize::new(460.0, 560.0))
+            .with_resizable(false)
+            .build(target)?;
+
+        // The IPC handler runs on the event loop thread but outside our
+        // closure, so requests are queued and picked up by `poll`
+        let (sender, requests) = mpsc::channel();
+        let builder = WebViewBuilder::new()
+            .with_html(PAGE)
+            .with_initialization_script(&format!(
+                "window.initialForm = {}; window.strings = {};",
+                serde_json::to_string(form)?,
+                serde_json::to_string(&i18n::messages_with_prefix("prefs-"))?
+            ))
+            .with_ipc_handler(move |request| match serde_json::from_str(request.body()) {
+                Ok(request) => {
+                    let _ = sender.send(request);
+                }
+                Err(e) => tracing::warn!("Ignoring preferences message: {}", e),
+            });
+
+        #[cfg(target_os = "linux")]
+        let webview = {
+            use tao::platform::unix::WindowExtUnix;
+            use wry::WebViewBuilderExtUnix;
+            builder.build_gtk(window.default_vbox().ok_or("window has no GTK container")?)?
+        };
+
+        #[cfg(not(target_os = "linux"))]
+        let webview = builder.build(&window)?;
+
+        window.set_focus();
+        Ok(Self {
+            window,
+            webview,
+            requests,
+        })
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    pub fn focus(&self) {
+        self.window.set_focus();
+    }
+
+    pub fn poll(&self) -> Option<PreferencesRequest> {
+        self.requests.try_recv().ok()
+    }
+
+    /// List validation problems above the buttons.
+    pub fn show_errors(&self, errors: &[String]) {
+        if let Ok(errors) = serde_json::to_string(errors) {
+            let _ = self.webview.evaluate_script(&format!("showErrors({});", errors));
+        }
+    }
+
+    pub fn set_binary_path(&self, path: &Path) {
+        if let Ok(path) = serde_json::to_string(&path.to_string_lossy()) {
+            let _ = self.webview.evaluate_script(&format!("setBinaryPath({});", path));
+        }
+    }
+}
+
+/// Native file picker for the lapsus_rust binary, starting next to `current`.
+pub fn pick_binary(current: &Path) -> Option<PathBuf> {
+    let mut dialog = rfd::FileDialog::new().set_title(t!("dialog-choose-binary"));
+    if let Some(dir) = current.parent().filter(|dir| dir.is_dir()) {
+        dialog = dialog.set_directory(dir);
+    }
+    dialog.pick_file()
+}
@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+
+use crate::foreground::ForegroundApp;
+use crate::i18n::t;
+
+/// What a rule does to lapsus_rust while its app is frontmost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    Enable,
+    Disable,
+}
+
+/// A frontmost-application rule, e.g. `{"app": "Figma", "action": "disable"}`.
+/// `app` is matched case-insensitively against the app name and bundle id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppRule {
+    pub app: String,
+    pub action: RuleAction,
+}
+
+impl AppRule {
+    fn matches(&self, app: &ForegroundApp) -> bool {
+        let wanted = self.app.trim();
+        app.name.eq_ignore_ascii_case(wanted)
+            || app
+                .bundle_id
+                .as_deref()
+                .is_some_and(|b| b.eq_ignore_ascii_case(wanted))
+    }
+}
+
+/// What the engine wants done to lapsus_rust.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleDecision {
+    Start,
+    Stop,
+}
+
+impl RuleDecision {
+    /// The change needed to go from `running` to `want_running`, if any.
+    pub fn towards(running: bool, want_running: bool) -> Option<Self> {
+        match (running, want_running) {
+            (false, true) => Some(Self::Start),
+            (true, false) => Some(Self::Stop),
+            _ => None,
+        }
+    }
+}
+
+/// The rule currently holding lapsus_rust in a forced state.
+#[derive(Debug, Clone)]
+pub struct ActiveOverride {
+    pub app_name: String,
+    pub action: RuleAction,
+    /// Whether lapsus_rust was running before the first rule kicked in.
+    baseline: bool,
+}
+
+impl ActiveOverride {
+    pub fn label(&self) -> String {
+        match self.action {
+            RuleAction::Disable => t!("rule-disabled", app = self.app_name),
+            RuleAction::Enable => t!("rule-enabled", app = self.app_name),
+        }
+    }
+}
+
+pub struct RulesEngine {
+    rules: Vec<AppRule>,
+    active: Option<ActiveOverride>,
+}
+
+impl RulesEngine {
+    pub fn new(rules: Vec<AppRule>) -> Self {
+        Self { rules, active: None }
+    }
+
+    pub fn active(&self) -> Option<&ActiveOverride> {
+        self.active.as_ref()
+    }
+
+    /// Forget the current override, e.g. after the user toggled lapsus by hand,
+    /// so leaving the app does not undo their choice.
+    pub fn reset(&mut self) {
+        self.active = None;
+    }
+
+    /// Feed a foreground change. Returns what to do with lapsus_rust, if anything.
+    pub fn on_app_changed(&mut self, app: &ForegroundApp, running: bool) -> Option<RuleDecision> {
+        let rule = self.rules.iter().find(|r| r.matches(app));
+
+        match (rule, self.active.take()) {
+            (Some(rule), previous) => {
+                // Switching between rule apps keeps the original baseline so
+                // we restore what the user had before any of them.
+                let baseline = previous.map(|p| p.baseline).unwrap_or(running);
+                let want_running = rule.action == RuleAction::Enable;
+                self.active = Some(ActiveOverride {
+                    app_name: app.name.clone(),
+                    action: rule.action,
+                    baseline,
+                });
+                RuleDecision::towards(running, want_running)
+            }
+            (None, Some(previous)) => RuleDecision::towards(running, previous.baseline),
+            (None, None) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(name: &str, bundle_id: Option<&str>) -> ForegroundApp {
+        ForegroundApp {
+            name: name.to_string(),
+            bundle_id: bundle_id.map(str::to_string),
+        }
+    }
+
+    fn rule(app: &str, action: RuleAction) -> AppRule {
+        AppRule {
+            app: app.to_string(),
+            action,
+        }
+    }
+
+    #[test]
+    fn towards_only_changes_what_differs() {
+        assert_eq!(RuleDecision::towards(false, true), Some(RuleDecision::Start));
+        assert_eq!(RuleDecision::towards(true, false), Some(RuleDecision::Stop));
+        assert_eq!(RuleDecision::towards(true, true), None);
+        assert_eq!(RuleDecision::towards(false, false), None);
+    }
+
+    #[test]
+    fn matches_name_or_bundle_id_ignoring_case() {
+        let figma = app("Figma", Some("com.figma.Desktop"));
+        assert!(rule("figma", RuleAction::Disable).matches(&figma));
+        assert!(rule(" Figma ", RuleAction::Disable).matches(&figma));
+        assert!(rule("COM.FIGMA.DESKTOP", RuleAction::Disable).matches(&figma));
+        assert!(!rule("Fig", RuleAction::Disable).matches(&figma));
+        assert!(!rule("com.figma.Desktop", RuleAction::Disable).matches(&app("Figma", None)));
+    }
+
+    #[test]
+    fn restores_the_baseline_when_leaving_the_app() {
+        let mut engine = RulesEngine::new(vec![rule("Figma", RuleAction::Disable)]);
+        assert_eq!(engine.on_app_changed(&app("Finder", None), true), None);
+
+        assert_eq!(engine.on_app_changed(&app("Figma", None), true), Some(RuleDecision::Stop));
+        assert_eq!(engine.active().map(ActiveOverride::label).as_deref(), Some("Disabled while Figma is active"));
+        assert_eq!(engine.on_app_changed(&app("Finder", None), false), Some(RuleDecision::Start));
+        assert!(engine.active().is_none());
+
+        // Already off before Figma: left off afterwards
+        assert_eq!(engine.on_app_changed(&app("Figma", None), false), None);
+        assert_eq!(engine.on_app_changed(&app("Finder", None), false), None);
+    }
+
+    #[test]
+    fn switching_between_rule_apps_keeps_the_first_baseline() {
+        let mut engine = RulesEngine::new(vec![
+            rule("Figma", RuleAction::Disable),
+            rule("Blender", RuleAction::Enable),
+        ]);
+        assert_eq!(engine.on_app_changed(&app("Figma", None), true), Some(RuleDecision::Stop));
+        assert_eq!(engine.on_app_changed(&app("Blender", None), false), Some(RuleDecision::Start));
+        assert_eq!(engine.on_app_changed(&app("Figma", None), true), Some(RuleDecision::Stop));
+        // Back to what it was before Figma, not before Blender
+        assert_eq!(engine.on_app_changed(&app("Finder", None), false), Some(RuleDecision::Start));
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let mut engine = RulesEngine::new(vec![
+            rule("com.figma.Desktop", RuleAction::Enable),
+            rule("Figma", RuleAction::Disable),
+        ]);
+        let figma = app("Figma", Some("com.figma.Desktop"));
+        assert_eq!(engine.on_app_changed(&figma, false), Some(RuleDecision::Start));
+        assert_eq!(engine.active().map(|active| active.action), Some(RuleAction::Enable));
+    }
+
+    #[test]
+    fn reset_keeps_a_manual_toggle() {
+        let mut engine = RulesEngine::new(vec![rule("Figma", RuleAction::Disable)]);
+        assert_eq!(engine.on_app_changed(&app("Figma", None), true), Some(RuleDecision::Stop));
+        // The user enables it by hand while Figma is in front
+        engine.reset();
+        assert_eq!(engine.on_app_changed(&app("Finder", None), true), None);
+    }
+}
@@ -0,0 +1,320 @@
+use chrono::{Datelike, Local, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::t;
+use crate::rules::{RuleAction, RuleDecision};
+
+/// A scheduled window as stored in the config, e.g.
+/// `{"days": ["mon", "tue"], "start": "09:00", "end": "10:00", "action": "disable"}`.
+/// Empty `days` means every day. A window whose end is before its start runs
+/// past midnight and belongs to the day it starts on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<String>,
+    pub start: String,
+    pub end: String,
+    pub action: RuleAction,
+}
+
+impl ScheduleEntry {
+    /// Checks days and times the way the scheduler will read them.
+    pub fn validate(&self) -> Result<(), String> {
+        Window::parse(self).map(|_| ())
+    }
+}
+
+/// Source of local wall-clock time, swappable for tests.
+pub trait Clock: Send {
+    fn now(&self) -> NaiveDateTime;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+}
+
+struct Window {
+    days: Vec<Weekday>,
+    start: NaiveTime,
+    end: NaiveTime,
+    action: RuleAction,
+}
+
+impl Window {
+    fn parse(entry: &ScheduleEntry) -> Result<Self, String> {
+        let days = entry
+            .days
+            .iter()
+            .map(|d| d.parse::<Weekday>().map_err(|_| t!("schedule-invalid-day", day = d)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let start = parse_time(&entry.start)?;
+        let end = parse_time(&entry.end)?;
+        if start == end {
+            return Err(t!("schedule-empty", start = entry.start, end = entry.end));
+        }
+
+        Ok(Self {
+            days,
+            start,
+            end,
+            action: entry.action,
+        })
+    }
+
+    fn applies_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    fn contains(&self, now: NaiveDateTime) -> bool {
+        let time = now.time();
+        let today = now.weekday();
+
+        if self.start < self.end {
+            self.applies_on(today) && time >= self.start && time < self.end
+        } else {
+            // Overnight: the evening part belongs to today, the morning part to yesterday
+            (self.applies_on(today) && time >= self.start)
+                || (self.applies_on(today.pred()) && time < self.end)
+        }
+    }
+}
+
+fn parse_time(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s.trim(), "%H:%M").map_err(|_| t!("schedule-invalid-time", time = s))
+}
+
+/// Applies scheduled windows at their boundaries only, so a manual
+/// enable/disable inside a window holds until the next boundary.
+pub struct Scheduler {
+    windows: Vec<Window>,
+    clock: Box<dyn Clock>,
+    current: Option<usize>,
+    /// Whether lapsus_rust was running before the current window began
+    baseline: Option<bool>,
+    overridden: bool,
+    evaluated: bool,
+}
+
+impl Scheduler {
+    pub fn new(entries: &[ScheduleEntry], clock: Box<dyn Clock>) -> Self {
+        let windows = entries
+            .iter()
+            .filter_map(|entry| match Window::parse(entry) {
+                Ok(window) => Some(window),
+                Err(e) => {
+                    tracing::warn!("Ignoring schedule entry: {}", e);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            windows,
+            clock,
+            current: None,
+            baseline: None,
+            overridden: false,
+            evaluated: false,
+        }
+    }
+
+    /// Check the clock. Returns what to do with lapsus_rust if a boundary
+    /// was crossed since the last call.
+    pub fn evaluate(&mut self, running: bool) -> Option<RuleDecision> {
+        let now = self.clock.now();
+        let active = self.windows.iter().position(|w| w.contains(now));
+        if self.evaluated && active == self.current {
+            return None;
+        }
+
+        self.evaluated = true;
+        self.overridden = false;
+        let previous = std::mem::replace(&mut self.current, active);
+
+        match active {
+            Some(index) => {
+                if previous.is_none() {
+                    self.baseline = Some(running);
+                }
+                let want_running = self.windows[index].action == RuleAction::Enable;
+                RuleDecision::towards(running, want_running)
+            }
+            None => {
+                let baseline = self.baseline.take()?;
+                RuleDecision::towards(running, baseline)
+            }
+        }
+    }
+
+    /// Record that the user toggled lapsus by hand inside the current window.
+    pub fn manual_override(&mut self) {
+        if self.current.is_some() {
+            self.overridden = true;
+        }
+    }
+
+    /// Menu text for the active window, e.g. "Scheduled: off until 10:00".
+    pub fn label(&self) -> Option<String> {
+        let window = &self.windows[self.current?];
+        let end = window.end.format("%H:%M");
+        let label = match window.action {
+            RuleAction::Enable => t!("schedule-on", end = end),
+            RuleAction::Disable => t!("schedule-off", end = end),
+        };
+        if self.overridden {
+            return Some(t!("schedule-overridden", label = label));
+        }
+        Some(label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// A clock the test moves by hand.
+    #[derive(Clone)]
+    struct FakeClock(Arc<Mutex<NaiveDateTime>>);
+
+    impl FakeClock {
+        fn at(when: &str) -> Self {
+            Self(Arc::new(Mutex::new(time(when))))
+        }
+
+        fn set(&self, when: &str) {
+            *self.0.lock().unwrap() = time(when);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> NaiveDateTime {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    /// 2026-03-06 is a Friday.
+    fn time(when: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(when, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn entry(days: &[&str], start: &str, end: &str, action: RuleAction) -> ScheduleEntry {
+        ScheduleEntry {
+            days: days.iter().map(|day| day.to_string()).collect(),
+            start: start.to_string(),
+            end: end.to_string(),
+            action,
+        }
+    }
+
+    fn scheduler(entries: &[ScheduleEntry], clock: &FakeClock) -> Scheduler {
+        Scheduler::new(entries, Box::new(clock.clone()))
+    }
+
+    #[test]
+    fn acts_at_boundaries_and_restores_the_baseline() {
+        let clock = FakeClock::at("2026-03-06 08:59");
+        let mut scheduler = scheduler(&[entry(&[], "09:00", "10:00", RuleAction::Disable)], &clock);
+
+        assert_eq!(scheduler.evaluate(true), None);
+        clock.set("2026-03-06 09:00");
+        assert_eq!(scheduler.evaluate(true), Some(RuleDecision::Stop));
+        assert_eq!(scheduler.label().as_deref(), Some("Scheduled: off until 10:00"));
+        // Nothing more inside the window
+        clock.set("2026-03-06 09:30");
+        assert_eq!(scheduler.evaluate(false), None);
+        // The end is exclusive
+        clock.set("2026-03-06 10:00");
+        assert_eq!(scheduler.evaluate(false), Some(RuleDecision::Start));
+        assert_eq!(scheduler.label(), None);
+    }
+
+    #[test]
+    fn overnight_window_belongs_to_the_day_it_starts() {
+        let window = Window::parse(&entry(&["fri"], "22:00", "06:00", RuleAction::Disable)).unwrap();
+        assert!(!window.contains(time("2026-03-06 05:00")));
+        assert!(!window.contains(time("2026-03-06 21:59")));
+        assert!(window.contains(time("2026-03-06 22:00")));
+        assert!(window.contains(time("2026-03-06 23:59")));
+        // Across midnight into Saturday
+        assert!(window.contains(time("2026-03-07 00:00")));
+        assert!(window.contains(time("2026-03-07 05:59")));
+        assert!(!window.contains(time("2026-03-07 06:00")));
+        assert!(!window.contains(time("2026-03-07 22:00")));
+    }
+
+    #[test]
+    fn window_from_midnight_is_on_its_own_day() {
+        let window = Window::parse(&entry(&["sat"], "00:00", "01:00", RuleAction::Enable)).unwrap();
+        assert!(!window.contains(time("2026-03-06 23:59")));
+        assert!(window.contains(time("2026-03-07 00:00")));
+        assert!(!window.contains(time("2026-03-07 01:00")));
+    }
+
+    #[test]
+    fn invalid_entries_are_reported_and_skipped() {
+        assert_eq!(
+            entry(&["funday"], "09:00", "10:00", RuleAction::Enable).validate(),
+            Err("invalid day: funday".to_string())
+        );
+        assert_eq!(
+            entry(&[], "9am", "10:00", RuleAction::Enable).validate(),
+            Err("invalid time: 9am".to_string())
+        );
+        assert_eq!(
+            entry(&[], "25:00", "10:00", RuleAction::Enable).validate(),
+            Err("invalid time: 25:00".to_string())
+        );
+        assert_eq!(
+            entry(&[], "09:00", "09:00", RuleAction::Enable).validate(),
+            Err("empty window: 09:00-09:00".to_string())
+        );
+        assert!(entry(&["Mon", "tue"], " 09:00", "10:00", RuleAction::Enable).validate().is_ok());
+
+        let clock = FakeClock::at("2026-03-06 09:30");
+        let mut scheduler = scheduler(
+            &[
+                entry(&[], "09:00", "09:00", RuleAction::Enable),
+                entry(&[], "09:00", "10:00", RuleAction::Enable),
+            ],
+            &clock,
+        );
+        assert_eq!(scheduler.evaluate(false), Some(RuleDecision::Start));
+    }
+
+    #[test]
+    fn manual_override_holds_until_the_next_boundary() {
+        let clock = FakeClock::at("2026-03-06 09:00");
+        let mut scheduler = scheduler(
+            &[
+                entry(&[], "09:00", "10:00", RuleAction::Disable),
+                entry(&[], "10:00", "11:00", RuleAction::Disable),
+            ],
+            &clock,
+        );
+        assert_eq!(scheduler.evaluate(true), Some(RuleDecision::Stop));
+
+        // The user enables it again inside the window
+        scheduler.manual_override();
+        assert_eq!(scheduler.label().as_deref(), Some("Scheduled: off until 10:00 (overridden)"));
+        clock.set("2026-03-06 09:59");
+        assert_eq!(scheduler.evaluate(true), None);
+
+        // The next window takes over again
+        clock.set("2026-03-06 10:00");
+        assert_eq!(scheduler.evaluate(true), Some(RuleDecision::Stop));
+        assert_eq!(scheduler.label().as_deref(), Some("Scheduled: off until 11:00"));
+
+        // Back to what it was before the first window
+        clock.set("2026-03-06 11:00");
+        assert_eq!(scheduler.evaluate(false), Some(RuleDecision::Start));
+
+        // Outside a window there is nothing to override
+        scheduler.manual_override();
+        assert_eq!(scheduler.label(), None);
+    }
+}
@@ -0,0 +1,622 @@
+#[cfg(not(target_os = "linux"))]
+use auto_launch::AutoLaunchBuilder;
+use serde::{Deserialize, Serialize};
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+use std::fs;
+use std::path::PathBuf;
+#[cfg(target_os = "linux")]
+use std::process::Command;
+
+/// launchd label of lapsus_rust's LaunchAgent, also used by `start_lapsus`.
+pub const DAEMON_ID: &str = "com.lapsus.rust";
+pub const DAEMON_UNIT: &str = "lapsus-rust";
+pub const MENUBAR_ID: &str = "com.lapsus.menubar";
+pub const MENUBAR_UNIT: &str = "lapsus-menubar";
+
+/// How the menu bar app starts at login on Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinuxLoginBackend {
+    /// `~/.config/autostart/lapsus-menubar.desktop`
+    #[default]
+    Xdg,
+    /// `~/.config/systemd/user/lapsus-menubar.service`
+    Systemd,
+}
+
+/// A program the OS starts at login.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginEntry {
+    /// launchd label
+    pub id: &'static str,
+    /// systemd unit and .desktop file name, without the extension
+    pub unit: &'static str,
+    /// Shown in login item lists
+    pub name: String,
+    pub program: PathBuf,
+    pub args: Vec<String>,
+}
+
+/// One way of starting programs at login: login items, a LaunchAgent,
+/// a systemd user unit or an XDG autostart entry.
+pub trait LoginBackend: Send {
+    fn is_enabled(&self, entry: &LoginEntry) -> Result<bool, Box<dyn std::error::Error>>;
+    fn set_enabled(&self, entry: &LoginEntry, enable: bool) -> Result<(), Box<dyn std::error::Error>>;
+    /// The program the registration launches, to notice a moved app.
+    fn registered_program(&self, entry: &LoginEntry) -> Option<PathBuf>;
+    /// Points a registration that launches another program, e.g. a moved
+    /// copy of the app, at `entry.program`.
+    fn repair(&self, entry: &LoginEntry) -> Result<(), Box<dyn std::error::Error>> {
+        self.set_enabled(entry, false)?;
+        self.set_enabled(entry, true)
+    }
+    /// Whether reading the registration may show a permission prompt, so
+    /// it is only read once the user has turned the setting on.
+    fn needs_permission(&self) -> bool {
+        false
+    }
+}
+
+/// Login items (macOS) or the Run registry key (Windows), through
+/// `auto-launch`.
+#[cfg(not(target_os = "linux"))]
+pub struct AutoLaunchBackend;
+
+#[cfg(not(target_os = "linux"))]
+impl AutoLaunchBackend {
+    fn launcher(entry: &LoginEntry) -> Result<auto_launch::AutoLaunch, Box<dyn std::error::Error>> {
+        Ok(AutoLaunchBuilder::new()
+            .set_app_name(&entry.name)
+            .set_app_path(&entry.program.to_string_lossy())
+            .set_use_launch_agent(false)
+            .set_args(entry.args.as_slice())
+            .build()?)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl LoginBackend for AutoLaunchBackend {
+    fn is_enabled(&self, entry: &LoginEntry) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(Self::launcher(entry)?.is_enabled()?)
+    }
+
+    fn set_enabled(&self, entry: &LoginEntry, enable: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let launcher = Self::launcher(entry)?;
+        if enable {
+            launcher.enable()?;
+        } else {
+            launcher.disable()?;
+        }
+        Ok(())
+    }
+
+    /// Asks System Events for the login item's path.
+    #[cfg(target_os = "macos")]
+    fn registered_program(&self, entry: &LoginEntry) -> Option<PathBuf> {
+        let launcher = Self::launcher(entry).ok()?;
+        let output = std::process::Command::new("osascript")
+            .arg("-e")
+            .arg("on run argv")
+            .arg("-e")
+            .arg("tell application \"System Events\" to get the path of login item (item 1 of argv)")
+            .arg("-e")
+            .arg("end run")
+            .arg(launcher.get_app_name())
+            .output()
+            .ok()?;
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !path.is_empty()).then(|| PathBuf::from(path))
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn registered_program(&self, _entry: &LoginEntry) -> Option<PathBuf> {
+        None
+    }
+
+    /// macOS login items are only readable through System Events, which
+    /// asks for Automation permission the first time.
+    fn needs_permission(&self) -> bool {
+        cfg!(target_os = "macos")
+    }
+}
+
+/// A LaunchAgent with `RunAtLoad` (macOS). An existing plist is only
+/// switched on or off, so a hand-written one keeps its other keys.
+#[cfg(target_os = "macos")]
+pub struct LaunchdBackend;
+
+#[cfg(target_os = "macos")]
+impl LaunchdBackend {
+    pub fn plist_path(id: &str) -> Option<PathBuf> {
+        Some(dirs::home_dir()?.join("Library/LaunchAgents").join(format!("{}.plist", id)))
+    }
+
+    fn plutil(args: &[&str], plist: &std::path::Path) -> Result<String, Box<dyn std::error::Error>> {
+        let output = std::process::Command::new("plutil").args(args).arg(plist).output()?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string().into());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// The user's GUI domain, e.g. "gui/501".
+    fn domain() -> Result<String, Box<dyn std::error::Error>> {
+        let output = std::process::Command::new("id").arg("-u").output()?;
+        let uid = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !output.status.success() || uid.is_empty() {
+            return Err("Cannot find the user id".into());
+        }
+        Ok(format!("gui/{}", uid))
+    }
+
+    fn launchctl(args: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+        tracing::debug!("launchctl {}", args.join(" "));
+        let output = std::process::Command::new("launchctl").args(args).output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("launchctl {} failed: {}", args[0], stderr.trim()).into());
+        }
+        Ok(())
+    }
+
+    fn is_loaded(service: &str) -> bool {
+        std::process::Command::new("launchctl")
+            .args(["print", service])
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    /// Loads the LaunchAgent `id` if needed and starts its job now,
+    /// whatever its `RunAtLoad` says.
+    pub fn start(id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let domain = Self::domain()?;
+        let service = format!("{}/{}", domain, id);
+        if !Self::is_loaded(&service) {
+            let plist = Self::plist_path(id).ok_or("Cannot find home directory")?;
+            Self::launchctl(&["bootstrap", &domain, &plist.to_string_lossy()])?;
+        }
+        Self::launchctl(&["kickstart", &service])
+    }
+
+    /// Stops the job `id` and unloads it until the next login or `start`.
+    /// Returns false if it wasn't loaded.
+    pub fn stop(id: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let service = format!("{}/{}", Self::domain()?, id);
+        if !Self::is_loaded(&service) {
+            return Ok(false);
+        }
+        Self::launchctl(&["bootout", &service])?;
+        Ok(true)
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl LoginBackend for LaunchdBackend {
+    fn is_enabled(&self, entry: &LoginEntry) -> Result<bool, Box<dyn std::error::Error>> {
+        let plist = Self::plist_path(entry.id).ok_or("Cannot find home directory")?;
+        if !plist.exists() {
+            return Ok(false);
+        }
+        // A missing key means false, which plutil reports as an error
+        Ok(Self::plutil(&["-extract", "RunAtLoad", "raw", "-o", "-"], &plist).is_ok_and(|value| value == "true"))
+    }
+
+    fn set_enabled(&self, entry: &LoginEntry, enable: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let plist = Self::plist_path(entry.id).ok_or("Cannot find home directory")?;
+        if plist.exists() {
+            let value = if enable { "YES" } else { "NO" };
+            Self::plutil(&["-replace", "RunAtLoad", "-bool", value], &plist)?;
+            if enable {
+                // Follow a moved binary or changed arguments
+                let mut arguments = vec![entry.program.to_string_lossy().into_owned()];
+                arguments.extend(entry.args.iter().cloned());
+                let json = serde_json::to_string(&arguments)?;
+                Self::plutil(&["-replace", "ProgramArguments", "-json", &json], &plist)?;
+            }
+        } else if enable {
+            fs::create_dir_all(plist.parent().ok_or("Invalid LaunchAgents path")?)?;
+            fs::write(&plist, launch_agent(entry))?;
+        }
+        Ok(())
+    }
+
+    /// Only the program: the plist may have been written by hand, with
+    /// arguments and keys of its own.
+    fn repair(&self, entry: &LoginEntry) -> Result<(), Box<dyn std::error::Error>> {
+        let plist = Self::plist_path(entry.id).ok_or("Cannot find home directory")?;
+        let program = entry.program.to_string_lossy();
+        Self::plutil(&["-replace", "ProgramArguments.0", "-string", &program], &plist)
+            .or_else(|_| Self::plutil(&["-replace", "Program", "-string", &program], &plist))?;
+        Ok(())
+    }
+
+    fn registered_program(&self, entry: &LoginEntry) -> Option<PathBuf> {
+        let plist = Self::plist_path(entry.id)?;
+        Self::plutil(&["-extract", "ProgramArguments.0", "raw", "-o", "-"], &plist)
+            .or_else(|_| Self::plutil(&["-extract", "Program", "raw", "-o", "-"], &plist))
+            .ok()
+            .map(PathBuf::from)
+    }
+}
+
+/// A systemd user unit tied to the graphical session (Linux).
+#[cfg(target_os = "linux")]
+pub struct SystemdUserBackend;
+
+#[cfg(target_os = "linux")]
+impl SystemdUserBackend {
+    /// True when the user session is managed by systemd.
+    pub fn available() -> bool {
+        std::path::Path::new("/run/systemd/system").exists()
+            && Command::new("systemctl").args(["--user", "--version"]).output().is_ok_and(|o| o.status.success())
+    }
+
+    fn unit_path(unit: &str) -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("systemd/user").join(format!("{}.service", unit)))
+    }
+
+    fn systemctl(args: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+        tracing::debug!("systemctl --user {}", args.join(" "));
+        let output = Command::new("systemctl").arg("--user").args(args).output()?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string().into());
+        }
+        Ok(())
+    }
+
+    /// Whether `unit` has been written, and systemd runs the user session.
+    pub fn unit_exists(unit: &str) -> bool {
+        Self::unit_path(unit).is_some_and(|path| path.exists()) && Self::available()
+    }
+
+    /// Starts `unit` now, whether or not it is enabled at login.
+    pub fn start(unit: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Self::systemctl(&["start", &format!("{}.service", unit)])
+    }
+
+    /// Stops `unit`. Returns false if it wasn't running.
+    pub fn stop(unit: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let unit = format!("{}.service", unit);
+        if Self::systemctl(&["is-active", "--quiet", &unit]).is_err() {
+            return Ok(false);
+        }
+        Self::systemctl(&["stop", &unit])?;
+        Ok(true)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl LoginBackend for SystemdUserBackend {
+    fn is_enabled(&self, entry: &LoginEntry) -> Result<bool, Box<dyn std::error::Error>> {
+        let unit = format!("{}.service", entry.unit);
+        // Exits non-zero for "disabled" and for unknown units alike
+        let output = Command::new("systemctl").args(["--user", "is-enabled", &unit]).output()?;
+        Ok(output.status.success())
+    }
+
+    fn set_enabled(&self, entry: &LoginEntry, enable: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::unit_path(entry.unit).ok_or("Cannot find config directory")?;
+        let unit = format!("{}.service", entry.unit);
+        if enable {
+            fs::create_dir_all(path.parent().ok_or("Invalid unit path")?)?;
+            fs::write(&path, systemd_unit(entry))?;
+            Self::systemctl(&["daemon-reload"])?;
+            Self::systemctl(&["enable", &unit])
+        } else {
+            if path.exists() {
+                Self::systemctl(&["disable", &unit])?;
+                fs::remove_file(&path)?;
+                Self::systemctl(&["daemon-reload"])?;
+            }
+            Ok(())
+        }
+    }
+
+    fn registered_program(&self, entry: &LoginEntry) -> Option<PathBuf> {
+        unit_program(&fs::read_to_string(Self::unit_path(entry.unit)?).ok()?)
+    }
+}
+
+/// An XDG autostart entry in `~/.config/autostart` (Linux desktops
+/// without systemd user sessions).
+#[cfg(target_os = "linux")]
+pub struct XdgAutostartBackend;
+
+#[cfg(target_os = "linux")]
+impl XdgAutostartBackend {
+    fn entry_path(unit: &str) -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("autostart").join(format!("{}.desktop", unit)))
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl LoginBackend for XdgAutostartBackend {
+    fn is_enabled(&self, entry: &LoginEntry) -> Result<bool, Box<dyn std::error::Error>> {
+        let path = Self::entry_path(entry.unit).ok_or("Cannot find config directory")?;
+        let Ok(content) = fs::read_to_string(path) else {
+            return Ok(false);
+        };
+        // Desktops turn an entry off with Hidden=true rather than deleting it
+        Ok(!content.lines().any(|line| line.trim() == "Hidden=true"))
+    }
+
+    fn set_enabled(&self, entry: &LoginEntry, enable: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::entry_path(entry.unit).ok_or("Cannot find config directory")?;
+        if enable {
+            fs::create_dir_all(path.parent().ok_or("Invalid autostart path")?)?;
+            fs::write(&path, desktop_entry(entry))?;
+        } else if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        Ok(())
+    }
+
+    fn registered_program(&self, entry: &LoginEntry) -> Option<PathBuf> {
+        desktop_program(&fs::read_to_string(Self::entry_path(entry.unit)?).ok()?)
+    }
+}
+
+/// The Linux backend actually used for `configured`: systemd only when the
+/// user session runs under it.
+#[cfg(target_os = "linux")]
+fn resolve(configured: LinuxLoginBackend) -> LinuxLoginBackend {
+    if configured == LinuxLoginBackend::Systemd && !SystemdUserBackend::available() {
+        return LinuxLoginBackend::Xdg;
+    }
+    configured
+}
+
+/// Backend for the menu bar app's own login item: a login item on macOS,
+/// the configured autostart entry or systemd unit on Linux.
+pub fn menubar_backend(linux: LinuxLoginBackend) -> Box<dyn LoginBackend> {
+    #[cfg(target_os = "linux")]
+    let backend: Box<dyn LoginBackend> = match resolve(linux) {
+        LinuxLoginBackend::Xdg if linux == LinuxLoginBackend::Systemd => {
+            tracing::warn!("No systemd user session, starting at login through XDG autostart instead");
+            Box::new(XdgAutostartBackend)
+        }
+        LinuxLoginBackend::Xdg => Box::new(XdgAutostartBackend),
+        LinuxLoginBackend::Systemd => Box::new(SystemdUserBackend),
+    };
+
+    #[cfg(not(target_os = "linux"))]
+    let backend: Box<dyn LoginBackend> = {
+        let _ = linux;
+        Box::new(AutoLaunchBackend)
+    };
+
+    backend
+}
+
+/// The backends `menubar_backend` doesn't use but the menu bar app may
+/// still be registered through, from before `linux_login_backend` changed.
+pub fn previous_menubar_backends(linux: LinuxLoginBackend) -> Vec<Box<dyn LoginBackend>> {
+    #[cfg(target_os = "linux")]
+    let backends: Vec<Box<dyn LoginBackend>> = match resolve(linux) {
+        LinuxLoginBackend::Xdg if SystemdUserBackend::available() => vec![Box::new(SystemdUserBackend)],
+        LinuxLoginBackend::Xdg => Vec::new(),
+        LinuxLoginBackend::Systemd => vec![Box::new(XdgAutostartBackend)],
+    };
+
+    #[cfg(not(target_os = "linux"))]
+    let backends: Vec<Box<dyn LoginBackend>> = {
+        let _ = linux;
+        Vec::new()
+    };
+
+    backends
+}
+
+/// Removes the autostart entry older versions registered through
+/// `auto-launch`, named after the app. Returns true if there was one, so
+/// the caller can register the new entry in its place.
+#[cfg(target_os = "linux")]
+pub fn remove_legacy_autostart(app_name: &str) -> bool {
+    let Some(path) = dirs::config_dir().map(|dir| dir.join("autostart").join(format!("{}.desktop", app_name)))
+    else {
+        return false;
+    };
+    path.exists() && fs::remove_file(path).is_ok()
+}
+
+/// Backend for starting lapsus_rust at login: its LaunchAgent on macOS,
+/// a systemd user unit or else an autostart entry on Linux.
+pub fn daemon_backend() -> Box<dyn LoginBackend> {
+    #[cfg(target_os = "macos")]
+    let backend: Box<dyn LoginBackend> = Box::new(LaunchdBackend);
+
+    #[cfg(target_os = "linux")]
+    let backend: Box<dyn LoginBackend> = if SystemdUserBackend::available() {
+        Box::new(SystemdUserBackend)
+    } else {
+        Box::new(XdgAutostartBackend)
+    };
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    let backend: Box<dyn LoginBackend> = Box::new(AutoLaunchBackend);
+
+    backend
+}
+
+#[cfg(target_os = "macos")]
+fn launch_agent(entry: &LoginEntry) -> String {
+    let escape = |text: &str| text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let mut arguments = format!("        <string>{}</string>\n", escape(&entry.program.to_string_lossy()));
+    for arg in &entry.args {
+        arguments.push_str(&format!("        <string>{}</string>\n", escape(arg)));
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n\
+         \x20   <key>Label</key>\n\
+         \x20   <string>{}</string>\n\
+         \x20   <key>ProgramArguments</key>\n\
+         \x20   <array>\n\
+         {}\
+         \x20   </array>\n\
+         \x20   <key>RunAtLoad</key>\n\
+         \x20   <true/>\n\
+         </dict>\n\
+         </plist>\n",
+        entry.id, arguments
+    )
+}
+
+/// Quotes a word when needed, escaping `escaped` inside the double quotes
+/// with a backslash.
+#[cfg(any(target_os = "linux", test))]
+fn quote(word: &str, escaped: &str) -> String {
+    let plain = !word.is_empty()
+        && !word.contains(|c: char| c.is_whitespace() || "\"'\\`$;&|<>()*?#~=%".contains(c));
+    if plain {
+        return word.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in word.chars() {
+        if escaped.contains(c) {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The program and its arguments as one line, each word quoted with
+/// `escaped` as for `quote`.
+#[cfg(any(target_os = "linux", test))]
+fn command_line(entry: &LoginEntry, escaped: &str) -> String {
+    std::iter::once(entry.program.to_string_lossy().into_owned())
+        .chain(entry.args.iter().cloned())
+        .map(|word| quote(&word, escaped))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn systemd_unit(entry: &LoginEntry) -> String {
+    format!(
+        "[Unit]\n\
+         Description={}\n\
+         PartOf=graphical-session.target\n\
+         After=graphical-session.target\n\
+         \n\
+         [Service]\n\
+         ExecStart={}\n\
+         \n\
+         [Install]\n\
+         WantedBy=graphical-session.target\n",
+        entry.name,
+        // systemd expands `%` specifiers and `$` variables, even in quotes
+        command_line(entry, "\"\\").replace('%', "%%").replace('$', "$$")
+    )
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn desktop_entry(entry: &LoginEntry) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name={}\n\
+         Exec={}\n\
+         Terminal=false\n\
+         X-GNOME-Autostart-enabled=true\n",
+        entry.name,
+        // Field codes start with `%`, and string values escape `\` once more
+        command_line(entry, "\"\\`$").replace('%', "%%").replace('\\', "\\\\")
+    )
+}
+
+/// The program a unit written by `systemd_unit` starts.
+#[cfg(any(target_os = "linux", test))]
+fn unit_program(unit: &str) -> Option<PathBuf> {
+    let exec = unit.lines().find_map(|line| line.strip_prefix("ExecStart="))?;
+    first_word(&exec.replace("%%", "%").replace("$$", "$"))
+}
+
+/// The program an entry written by `desktop_entry` starts.
+#[cfg(any(target_os = "linux", test))]
+fn desktop_program(entry: &str) -> Option<PathBuf> {
+    let exec = entry.lines().find_map(|line| line.strip_prefix("Exec="))?;
+    first_word(&exec.replace("\\\\", "\\").replace("%%", "%"))
+}
+
+/// First word of a command line written by `command_line`, once the
+/// file format's own escaping is undone.
+#[cfg(any(target_os = "linux", test))]
+fn first_word(line: &str) -> Option<PathBuf> {
+    let line = line.trim();
+    let Some(rest) = line.strip_prefix('"') else {
+        return line.split_whitespace().next().map(PathBuf::from);
+    };
+    let mut word = String::new();
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => word.extend(chars.next()),
+            '"' => return Some(PathBuf::from(word)),
+            c => word.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn menubar() -> LoginEntry {
+        LoginEntry {
+            id: MENUBAR_ID,
+            unit: MENUBAR_UNIT,
+            name: "Lapsus Control".to_string(),
+            program: PathBuf::from("/usr/local/bin/lapsus-menubar"),
+            args: Vec::new(),
+        }
+    }
+
+    /// Arguments that need quoting or escaping in both formats.
+    fn daemon() -> LoginEntry {
+        LoginEntry {
+            id: DAEMON_ID,
+            unit: DAEMON_UNIT,
+            name: "lapsus_rust".to_string(),
+            program: PathBuf::from("/home/dev/My Tools/lapsus_rust"),
+            args: ["--speed", "50%", "--label=\"two words\"", "C:\\tmp", "$HOME"]
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn desktop_entries_match_fixtures() {
+        assert_eq!(desktop_entry(&menubar()), include_str!("../tests/fixtures/lapsus-menubar.desktop"));
+        assert_eq!(desktop_entry(&daemon()), include_str!("../tests/fixtures/lapsus-rust.desktop"));
+    }
+
+    #[test]
+    fn systemd_units_match_fixtures() {
+        assert_eq!(systemd_unit(&menubar()), include_str!("../tests/fixtures/lapsus-menubar.service"));
+        assert_eq!(systemd_unit(&daemon()), include_str!("../tests/fixtures/lapsus-rust.service"));
+    }
+
+    #[test]
+    fn reads_back_the_registered_program() {
+        for entry in [menubar(), daemon()] {
+            assert_eq!(desktop_program(&desktop_entry(&entry)), Some(entry.program.clone()));
+            assert_eq!(unit_program(&systemd_unit(&entry)), Some(entry.program.clone()));
+        }
+    }
+
+    #[test]
+    fn quotes_only_when_needed() {
+        assert_eq!(quote("--speed", "\""), "--speed");
+        assert_eq!(quote("", "\""), "\"\"");
+        assert_eq!(quote("a \"b\"", "\""), "\"a \\\"b\\\"\"");
+        assert_eq!(first_word("/usr/bin/app --flag"), Some(PathBuf::from("/usr/bin/app")));
+        assert_eq!(first_word("\"/opt/my \\\"app\\\"\" --flag"), Some(PathBuf::from("/opt/my \"app\"")));
+    }
+}
@@ -0,0 +1,546 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Power and login-session events that tend to leave lapsus_rust in a bad state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    Wake,
+    ScreenLocked,
+    ScreenUnlocked,
+    SwitchedOut,
+    SwitchedIn,
+}
+
+/// What to do with lapsus_rust for a kind of session event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionAction {
+    /// Restart the daemon once the session is usable again
+    Restart,
+    /// Stop on lock/switch-out and start again on unlock/switch-in.
+    /// For wake, which has no "while", this behaves like `Nothing`.
+    StopWhileLocked,
+    Nothing,
+}
+
+/// Per-event policy, stored in the config as `session_policy`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionPolicy {
+    pub on_wake: SessionAction,
+    pub on_lock: SessionAction,
+    pub on_user_switch: SessionAction,
+}
+
+impl Default for SessionPolicy {
+    fn default() -> Self {
+        Self {
+            on_wake: SessionAction::Restart,
+            on_lock: SessionAction::Nothing,
+            on_user_switch: SessionAction::Restart,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionDecision {
+    Start,
+    Stop,
+    Restart,
+}
+
+/// Applies a `SessionPolicy` to a stream of session events.
+pub struct SessionPolicyEngine {
+    policy: SessionPolicy,
+    stopped_on_lock: bool,
+    stopped_on_switch: bool,
+}
+
+impl SessionPolicyEngine {
+    pub fn new(policy: SessionPolicy) -> Self {
+        Self {
+            policy,
+            stopped_on_lock: false,
+            stopped_on_switch: false,
+        }
+    }
+
+    pub fn on_event(&mut self, event: SessionEvent, running: bool) -> Option<SessionDecision> {
+        match event {
+            SessionEvent::Wake => match self.policy.on_wake {
+                SessionAction::Restart if running => Some(SessionDecision::Restart),
+                _ => None,
+            },
+            SessionEvent::ScreenLocked => {
+                Self::leave(self.policy.on_lock, running, &mut self.stopped_on_lock)
+            }
+            SessionEvent::ScreenUnlocked => {
+                Self::enter(self.policy.on_lock, running, &mut self.stopped_on_lock)
+            }
+            SessionEvent::SwitchedOut => {
+                Self::leave(self.policy.on_user_switch, running, &mut self.stopped_on_switch)
+            }
+            SessionEvent::SwitchedIn => {
+                Self::enter(self.policy.on_user_switch, running, &mut self.stopped_on_switch)
+            }
+        }
+    }
+
+    /// Forget that we stopped the daemon, e.g. after a manual toggle.
+    pub fn reset(&mut self) {
+        self.stopped_on_lock = false;
+        self.stopped_on_switch = false;
+    }
+
+    fn leave(action: SessionAction, running: bool, stopped: &mut bool) -> Option<SessionDecision> {
+        if action == SessionAction::StopWhileLocked && running {
+            *stopped = true;
+            return Some(SessionDecision::Stop);
+        }
+        None
+    }
+
+    fn enter(action: SessionAction, running: bool, stopped: &mut bool) -> Option<SessionDecision> {
+        match action {
+            SessionAction::StopWhileLocked if std::mem::take(stopped) && !running => {
+                Some(SessionDecision::Start)
+            }
+            SessionAction::Restart if running => Some(SessionDecision::Restart),
+            _ => None,
+        }
+    }
+}
+
+/// Platform hook that reports session events since the last poll.
+pub trait SessionEventSource {
+    fn poll(&mut self) -> Vec<SessionEvent>;
+}
+
+/// Detects wake from sleep by comparing wall-clock time with the monotonic
+/// clock, which does not advance while the machine sleeps. Works everywhere.
+pub struct WakeDetector {
+    last_instant: Instant,
+    last_wall: SystemTime,
+}
+
+/// Sleep shorter than this is indistinguishable from a busy event loop.
+const WAKE_GAP: Duration = Duration::from_secs(10);
+
+impl WakeDetector {
+    pub fn new() -> Self {
+        Self {
+            last_instant: Instant::now(),
+            last_wall: SystemTime::now(),
+        }
+    }
+}
+
+impl SessionEventSource for WakeDetector {
+    fn poll(&mut self) -> Vec<SessionEvent> {
+        let now_instant = Instant::now();
+        let now_wall = SystemTime::now();
+        let monotonic = now_instant.duration_since(self.last_instant);
+        let wall = now_wall.duration_since(self.last_wall).unwrap_or(Duration::ZERO);
+        self.last_instant = now_instant;
+        self.last_wall = now_wall;
+
+        if wall.saturating_sub(monotonic) > WAKE_GAP {
+            vec![SessionEvent::Wake]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Turns `(screen_locked, session_on_console)` samples into events for
+/// what changed between them.
+#[derive(Default)]
+struct SessionEdges {
+    last: Option<(bool, bool)>,
+}
+
+impl SessionEdges {
+    fn update(&mut self, (locked, on_console): (bool, bool)) -> Vec<SessionEvent> {
+        let mut events = Vec::new();
+        let (was_locked, was_on_console) = self.last.unwrap_or((locked, on_console));
+
+        if on_console != was_on_console {
+            events.push(if on_console {
+                SessionEvent::SwitchedIn
+            } else {
+                SessionEvent::SwitchedOut
+            });
+        }
+        if locked != was_locked {
+            events.push(if locked {
+                SessionEvent::ScreenLocked
+            } else {
+                SessionEvent::ScreenUnlocked
+            });
+        }
+
+        self.last = Some((locked, on_console));
+        events
+    }
+}
+
+/// Samples `(screen_locked, session_on_console)` at most once per
+/// `interval` and reports the edges as events. The sampler is platform
+/// specific.
+pub struct PolledSessionSource {
+    sample: fn() -> Option<(bool, bool)>,
+    interval: Duration,
+    sampled_at: Instant,
+    edges: SessionEdges,
+}
+
+impl PolledSessionSource {
+    pub fn new(sample: fn() -> Option<(bool, bool)>, interval: Duration) -> Self {
+        let mut edges = SessionEdges::default();
+        if let Some(first) = sample() {
+            edges.update(first);
+        }
+        Self {
+            sample,
+            interval,
+            sampled_at: Instant::now(),
+            edges,
+        }
+    }
+}
+
+impl SessionEventSource for PolledSessionSource {
+    fn poll(&mut self) -> Vec<SessionEvent> {
+        if self.sampled_at.elapsed() < self.interval {
+            return Vec::new();
+        }
+        self.sampled_at = Instant::now();
+        match (self.sample)() {
+            Some(sample) => self.edges.update(sample),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Reads the CoreGraphics session dictionary, which covers both screen lock
+/// and fast user switching.
+#[cfg(target_os = "macos")]
+fn sample_cg_session() -> Option<(bool, bool)> {
+    use core_foundation::base::{CFType, TCFType};
+    use core_foundation::boolean::CFBoolean;
+    use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+    use core_foundation::string::CFString;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGSessionCopyCurrentDictionary() -> CFDictionaryRef;
+    }
+
+    unsafe {
+        let dict_ref = CGSessionCopyCurrentDictionary();
+        if dict_ref.is_null() {
+            return None;
+        }
+        let dict: CFDictionary<CFString, CFType> = CFDictionary::wrap_under_create_rule(dict_ref);
+        let flag = |key: &str| {
+            dict.find(CFString::new(key))
+                .and_then(|value| value.downcast::<CFBoolean>())
+                .map(bool::from)
+        };
+        Some((
+            flag("CGSSessionScreenIsLocked").unwrap_or(false),
+            flag("kCGSSessionOnConsoleKey").unwrap_or(true),
+        ))
+    }
+}
+
+/// Follows our logind session over D-Bus: `LockedHint` for the lock
+/// screen and `Active` for whether another user's session is in the
+/// foreground. A worker thread waits for logind's change signals, so
+/// nothing is spawned or asked on each poll.
+#[cfg(target_os = "linux")]
+pub struct LogindSessionSource {
+    receiver: std::sync::mpsc::Receiver<(bool, bool)>,
+    edges: SessionEdges,
+}
+
+#[cfg(target_os = "linux")]
+impl LogindSessionSource {
+    const DESTINATION: &'static str = "org.freedesktop.login1";
+    const MANAGER_PATH: &'static str = "/org/freedesktop/login1";
+    const MANAGER: &'static str = "org.freedesktop.login1.Manager";
+    const SESSION: &'static str = "org.freedesktop.login1.Session";
+
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        use zbus::blocking::{fdo::PropertiesProxy, proxy::Builder, Connection, Proxy};
+        use zbus::proxy::CacheProperties;
+        use zbus::zvariant::OwnedObjectPath;
+
+        let connection = Connection::system()?;
+        let reply = match std::env::var("XDG_SESSION_ID") {
+            Ok(id) => {
+                connection.call_method(Some(Self::DESTINATION), Self::MANAGER_PATH, Some(Self::MANAGER), "GetSession", &(id,))
+            }
+            Err(_) => connection.call_method(
+                Some(Self::DESTINATION),
+                Self::MANAGER_PATH,
+                Some(Self::MANAGER),
+                "GetSessionByPID",
+                &(std::process::id(),),
+            ),
+        }?;
+        let path: OwnedObjectPath = reply.body().deserialize()?;
+
+        // Uncached: a cache fed by the same signals may not have caught up
+        // yet when the worker reads after one
+        let session: Proxy = Builder::new(&connection)
+            .destination(Self::DESTINATION)?
+            .path(path.clone())?
+            .interface(Self::SESSION)?
+            .cache_properties(CacheProperties::No)
+            .build()?;
+        let properties = PropertiesProxy::builder(&connection)
+            .destination(Self::DESTINATION)?
+            .path(path)?
+            .build()?;
+        let changes = properties.receive_properties_changed()?;
+
+        let sample = move || -> Result<(bool, bool), zbus::Error> {
+            Ok((session.get_property("LockedHint")?, session.get_property("Active")?))
+        };
+        let mut edges = SessionEdges::default();
+        edges.update(sample()?);
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::Builder::new().name("logind".to_string()).spawn(move || {
+            for change in changes {
+                if !change.args().is_ok_and(|args| args.interface_name == Self::SESSION) {
+                    continue;
+                }
+                match sample() {
+                    Ok(sample) if sender.send(sample).is_ok() => {}
+                    Ok(_) => break,
+                    Err(e) => tracing::warn!("Failed to read the logind session: {}", e),
+                }
+            }
+        })?;
+        Ok(Self { receiver, edges })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl SessionEventSource for LogindSessionSource {
+    fn poll(&mut self) -> Vec<SessionEvent> {
+        self.receiver.try_iter().flat_map(|sample| self.edges.update(sample)).collect()
+    }
+}
+
+/// How often `loginctl` is asked when logind can't be reached over D-Bus.
+#[cfg(target_os = "linux")]
+const LOGINCTL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Asks `loginctl` about our session, for when D-Bus isn't available:
+/// `LockedHint` for the lock screen and `Active` for whether another
+/// user's session is in the foreground.
+#[cfg(target_os = "linux")]
+fn sample_logind_session() -> Option<(bool, bool)> {
+    let session_id = std::env::var("XDG_SESSION_ID").ok()?;
+    let output = std::process::Command::new("loginctl")
+        .args(["show-session", &session_id, "-p", "LockedHint", "-p", "Active"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let property = |name: &str| {
+        stdout
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix('='))
+            .map(|value| value.trim() == "yes")
+    };
+    Some((property("LockedHint")?, property("Active")?))
+}
+
+/// Event source fed by hand through a channel, for exercising the policy
+/// without a real session.
+#[cfg(test)]
+pub struct FakeSessionSource {
+    receiver: std::sync::mpsc::Receiver<SessionEvent>,
+}
+
+#[cfg(test)]
+impl FakeSessionSource {
+    pub fn new() -> (Self, std::sync::mpsc::Sender<SessionEvent>) {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        (Self { receiver }, sender)
+    }
+}
+
+#[cfg(test)]
+impl SessionEventSource for FakeSessionSource {
+    fn poll(&mut self) -> Vec<SessionEvent> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+/// Merges several sources into one.
+pub struct SessionMonitor {
+    sources: Vec<Box<dyn SessionEventSource>>,
+}
+
+impl SessionMonitor {
+    pub fn new(sources: Vec<Box<dyn SessionEventSource>>) -> Self {
+        Self { sources }
+    }
+
+    pub fn poll(&mut self) -> Vec<SessionEvent> {
+        self.sources.iter_mut().flat_map(|s| s.poll()).collect()
+    }
+}
+
+/// Wake detection everywhere, plus lock and user-switch events from
+/// CoreGraphics on macOS and logind on Linux.
+pub fn platform_monitor() -> SessionMonitor {
+    #[allow(unused_mut)]
+    let mut sources: Vec<Box<dyn SessionEventSource>> = vec![Box::new(WakeDetector::new())];
+
+    // In-process and cheap, so on every poll
+    #[cfg(target_os = "macos")]
+    sources.push(Box::new(PolledSessionSource::new(sample_cg_session, Duration::ZERO)));
+
+    #[cfg(target_os = "linux")]
+    match LogindSessionSource::new() {
+        Ok(source) => sources.push(Box::new(source)),
+        Err(e) => {
+            tracing::warn!("Cannot follow the logind session, polling loginctl instead: {}", e);
+            sources.push(Box::new(PolledSessionSource::new(sample_logind_session, LOGINCTL_INTERVAL)));
+        }
+    }
+
+    SessionMonitor::new(sources)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(policy: SessionPolicy, events: &[SessionEvent]) -> Vec<Option<SessionDecision>> {
+        let (source, sender) = FakeSessionSource::new();
+        let mut monitor = SessionMonitor::new(vec![Box::new(source)]);
+        let mut engine = SessionPolicyEngine::new(policy);
+        let mut running = true;
+
+        for event in events {
+            sender.send(*event).unwrap();
+        }
+        monitor
+            .poll()
+            .into_iter()
+            .map(|event| {
+                let decision = engine.on_event(event, running);
+                match decision {
+                    Some(SessionDecision::Start) => running = true,
+                    Some(SessionDecision::Stop) => running = false,
+                    _ => {}
+                }
+                decision
+            })
+            .collect()
+    }
+
+    #[test]
+    fn default_policy_restarts_after_wake_and_user_switch() {
+        let decisions = run(
+            SessionPolicy::default(),
+            &[
+                SessionEvent::Wake,
+                SessionEvent::ScreenLocked,
+                SessionEvent::ScreenUnlocked,
+                SessionEvent::SwitchedOut,
+                SessionEvent::SwitchedIn,
+            ],
+        );
+        assert_eq!(
+            decisions,
+            vec![Some(SessionDecision::Restart), None, None, None, Some(SessionDecision::Restart)]
+        );
+    }
+
+    #[test]
+    fn stop_while_locked_restores_only_what_it_stopped() {
+        let policy = SessionPolicy {
+            on_wake: SessionAction::StopWhileLocked,
+            on_lock: SessionAction::StopWhileLocked,
+            on_user_switch: SessionAction::Nothing,
+        };
+        let decisions = run(
+            policy,
+            &[
+                SessionEvent::Wake,
+                SessionEvent::ScreenLocked,
+                SessionEvent::ScreenUnlocked,
+                SessionEvent::ScreenUnlocked,
+            ],
+        );
+        assert_eq!(
+            decisions,
+            vec![None, Some(SessionDecision::Stop), Some(SessionDecision::Start), None]
+        );
+    }
+
+    #[test]
+    fn manual_toggle_while_locked_is_not_undone() {
+        let policy = SessionPolicy {
+            on_lock: SessionAction::StopWhileLocked,
+            ..SessionPolicy::default()
+        };
+        let mut engine = SessionPolicyEngine::new(policy);
+        assert_eq!(
+            engine.on_event(SessionEvent::ScreenLocked, true),
+            Some(SessionDecision::Stop)
+        );
+        engine.reset();
+        assert_eq!(engine.on_event(SessionEvent::ScreenUnlocked, false), None);
+    }
+
+    #[test]
+    fn edges_become_events() {
+        let mut edges = SessionEdges::default();
+        assert!(edges.update((false, true)).is_empty());
+        assert_eq!(edges.update((true, true)), [SessionEvent::ScreenLocked]);
+        assert_eq!(
+            edges.update((false, false)),
+            [SessionEvent::SwitchedOut, SessionEvent::ScreenUnlocked]
+        );
+        assert!(edges.update((false, false)).is_empty());
+        assert_eq!(edges.update((false, true)), [SessionEvent::SwitchedIn]);
+    }
+
+    #[test]
+    fn polled_source_samples_once_per_interval() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static SAMPLES: AtomicUsize = AtomicUsize::new(0);
+        fn sample() -> Option<(bool, bool)> {
+            // Locked from the second sample on
+            Some((SAMPLES.fetch_add(1, Ordering::SeqCst) > 0, true))
+        }
+
+        let mut source = PolledSessionSource::new(sample, Duration::from_secs(3600));
+        assert!(source.poll().is_empty());
+        assert_eq!(SAMPLES.load(Ordering::SeqCst), 1);
+
+        source.interval = Duration::ZERO;
+        assert_eq!(source.poll(), [SessionEvent::ScreenLocked]);
+        assert_eq!(SAMPLES.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn policy_fills_missing_fields_with_defaults() {
+        let policy: SessionPolicy = serde_json::from_str(r#"{"on_lock": "stop_while_locked"}"#).unwrap();
+        assert_eq!(policy.on_lock, SessionAction::StopWhileLocked);
+        assert_eq!(policy.on_wake, SessionAction::Restart);
+    }
+}
@@ -0,0 +1,529 @@
+use chrono::{DateTime, Days, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::i18n::t;
+
+/// Events older than this are dropped when the history is opened.
+const KEEP_DAYS: u64 = 90;
+
+/// Days in the Statistics submenu, today first.
+pub const MENU_DAYS: usize = 7;
+
+/// How often `heartbeat` saves the time, in seconds.
+const HEARTBEAT_SECS: i64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Enabled,
+    Disabled,
+    Restarted,
+    /// Exited without being stopped
+    Crashed,
+}
+
+/// What made lapsus_rust start, stop or restart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// Menu, hotkey, tray icon click or command line
+    User,
+    Schedule,
+    AppRule,
+    Session,
+    Power,
+    Pause,
+    DisplaySwitch,
+    /// Brought back after a crash
+    Supervisor,
+    /// Restarted after failing a health check
+    Health,
+    /// Found running or stopped, or restored, when the app started
+    Launch,
+    Quit,
+}
+
+impl Source {
+    pub const ALL: [Source; 11] = [
+        Source::User,
+        Source::Schedule,
+        Source::AppRule,
+        Source::Session,
+        Source::Power,
+        Source::Pause,
+        Source::DisplaySwitch,
+        Source::Supervisor,
+        Source::Health,
+        Source::Launch,
+        Source::Quit,
+    ];
+
+    /// Name in the history file, also used as a metrics label.
+    pub fn id(self) -> &'static str {
+        match self {
+            Source::User => "user",
+            Source::Schedule => "schedule",
+            Source::AppRule => "app_rule",
+            Source::Session => "session",
+            Source::Power => "power",
+            Source::Pause => "pause",
+            Source::DisplaySwitch => "display_switch",
+            Source::Supervisor => "supervisor",
+            Source::Health => "health",
+            Source::Launch => "launch",
+            Source::Quit => "quit",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Event {
+    /// Unix time, seconds
+    pub at: i64,
+    pub kind: EventKind,
+    pub source: Source,
+}
+
+impl Event {
+    /// True for a restart of any kind, including after a crash.
+    pub fn is_restart(&self) -> bool {
+        self.kind == EventKind::Restarted || (self.kind == EventKind::Enabled && self.source == Source::Supervisor)
+    }
+}
+
+/// Append-only history of what happened to lapsus_rust, one JSON event
+/// per line.
+pub struct EventLog {
+    /// `None` keeps events in memory only
+    path: Option<PathBuf>,
+    events: Vec<Event>,
+    /// Unix time the app was last known to be watching, from `heartbeat`
+    seen: i64,
+}
+
+impl EventLog {
+    /// Loads the history at `path`, dropping events older than
+    /// `KEEP_DAYS` and lines that can't be read.
+    pub fn open(path: PathBuf) -> Self {
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        let oldest = Local::now().timestamp() - (KEEP_DAYS * 86_400) as i64;
+        let lines = content.lines().filter(|line| !line.trim().is_empty()).count();
+        let events: Vec<Event> = content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .filter(|event: &Event| event.at >= oldest)
+            .collect();
+
+        let seen = fs::read_to_string(seen_path(&path))
+            .ok()
+            .and_then(|seen| seen.trim().parse().ok())
+            .unwrap_or(0);
+
+        let log = Self {
+            path: Some(path),
+            events,
+            seen,
+        };
+        if log.events.len() != lines {
+            if let Err(e) = log.rewrite() {
+                tracing::warn!("Failed to prune the event history: {}", e);
+            }
+        }
+        log
+    }
+
+    /// History that is not saved, when there is nowhere to put it.
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            events: Vec::new(),
+            seen: 0,
+        }
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub fn record(&mut self, kind: EventKind, source: Source) {
+        self.record_at(Local::now().timestamp(), kind, source);
+    }
+
+    fn record_at(&mut self, at: i64, kind: EventKind, source: Source) {
+        self.push(Event { at, kind, source });
+    }
+
+    /// Records what was found at launch, if the history says otherwise:
+    /// lapsus_rust may have been started or stopped while the app wasn't
+    /// running. `started` is when the running lapsus_rust started, or
+    /// `None` if it isn't running.
+    ///
+    /// The app can't see what happened after it last looked, e.g. before
+    /// a crash or shutdown, so lapsus_rust counts as stopped from then
+    /// unless it has been running since.
+    pub fn observe(&mut self, started: Option<i64>) {
+        let seen = self.last_seen();
+        match (self.enabled(), started) {
+            (true, None) => self.record_at(seen, EventKind::Disabled, Source::Launch),
+            (true, Some(started)) if started > seen => {
+                self.record_at(seen, EventKind::Disabled, Source::Launch);
+                self.record_at(started, EventKind::Enabled, Source::Launch);
+            }
+            (false, Some(started)) => self.record_at(started.max(seen), EventKind::Enabled, Source::Launch),
+            _ => {}
+        }
+    }
+
+    /// Notes that the app is still watching. Call on every poll; the time
+    /// is saved at most once a minute.
+    pub fn heartbeat(&mut self) {
+        let now = Local::now().timestamp();
+        if now - self.seen < HEARTBEAT_SECS {
+            return;
+        }
+        self.seen = now;
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = fs::write(seen_path(path), now.to_string()) {
+            tracing::warn!("Failed to save the heartbeat: {}", e);
+        }
+    }
+
+    /// The last time the app is known to have been watching.
+    fn last_seen(&self) -> i64 {
+        self.events.last().map_or(self.seen, |event| event.at.max(self.seen))
+    }
+
+    /// Whether the history ends with lapsus_rust running.
+    fn enabled(&self) -> bool {
+        self.events
+            .iter()
+            .rev()
+            .find(|event| event.kind != EventKind::Restarted)
+            .is_some_and(|event| event.kind == EventKind::Enabled)
+    }
+
+    fn push(&mut self, event: Event) {
+        tracing::debug!(kind = ?event.kind, source = ?event.source, "Recorded event");
+        self.events.push(event);
+        if let Err(e) = self.append(&event) {
+            tracing::warn!("Failed to record {:?}: {}", event.kind, e);
+        }
+    }
+
+    fn append(&self, event: &Event) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(event)?)?;
+        Ok(())
+    }
+
+    fn rewrite(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut content = String::new();
+        for event in &self.events {
+            content.push_str(&serde_json::to_string(event)?);
+            content.push('\n');
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+/// Next to the history: `events.seen`, holding the last heartbeat.
+fn seen_path(path: &Path) -> PathBuf {
+    path.with_extension("seen")
+}
+
+/// `events.jsonl` in the app's data directory.
+pub fn default_path() -> Option<PathBuf> {
+    Some(dirs::data_local_dir()?.join("lapsus-menubar").join("events.jsonl"))
+}
+
+/// One day of history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaySummary {
+    pub date: NaiveDate,
+    /// Time lapsus_rust was running
+    pub enabled: Duration,
+    /// Restarts of any kind, including after crashes
+    pub restarts: u32,
+    /// Restarts because the cursor moved to another display
+    pub display_restarts: u32,
+    pub crashes: u32,
+}
+
+/// A summary for each of the `days` days up to and including the day of
+/// `now`, newest first.
+pub fn daily<Tz: TimeZone>(events: &[Event], days: usize, now: &DateTime<Tz>) -> Vec<DaySummary> {
+    let tz = now.timezone();
+    let local = |at: i64| tz.timestamp_opt(at, 0).single();
+    let today = now.date_naive();
+
+    // Periods lapsus_rust was running, as unix times
+    let mut periods = Vec::new();
+    let mut since: Option<i64> = None;
+    for event in events {
+        match event.kind {
+            EventKind::Enabled | EventKind::Restarted => {
+                since.get_or_insert(event.at);
+            }
+            EventKind::Disabled | EventKind::Crashed => {
+                if let Some(start) = since.take() {
+                    periods.push((start, event.at));
+                }
+            }
+        }
+    }
+    if let Some(start) = since {
+        periods.push((start, now.timestamp()));
+    }
+
+    (0..days as u64)
+        .filter_map(|back| today.checked_sub_days(Days::new(back)))
+        .map(|date| {
+            let start = day_start(&tz, date);
+            let end = date.succ_opt().map_or(i64::MAX, |next| day_start(&tz, next));
+            let overlap: i64 = periods
+                .iter()
+                .map(|&(from, to)| (to.min(end) - from.max(start)).max(0))
+                .sum();
+
+            let on_day: Vec<&Event> = events
+                .iter()
+                .filter(|event| local(event.at).is_some_and(|at| at.date_naive() == date))
+                .collect();
+            DaySummary {
+                date,
+                enabled: Duration::from_secs(overlap as u64),
+                restarts: on_day.iter().filter(|event| event.is_restart()).count() as u32,
+                display_restarts: on_day
+                    .iter()
+                    .filter(|event| event.kind == EventKind::Restarted && event.source == Source::DisplaySwitch)
+                    .count() as u32,
+                crashes: on_day.iter().filter(|event| event.kind == EventKind::Crashed).count() as u32,
+            }
+        })
+        .collect()
+}
+
+fn day_start<Tz: TimeZone>(tz: &Tz, date: NaiveDate) -> i64 {
+    let midnight = date.and_hms_opt(0, 0, 0).expect("midnight exists");
+    tz.from_local_datetime(&midnight)
+        .earliest()
+        .map_or_else(|| midnight.and_utc().timestamp(), |start| start.timestamp())
+}
+
+/// "Today", "Yesterday" or the date.
+fn day_label(date: NaiveDate, today: NaiveDate) -> String {
+    if date == today {
+        t!("stats-today")
+    } else if today.pred_opt() == Some(date) {
+        t!("stats-yesterday")
+    } else {
+        date.format("%Y-%m-%d").to_string()
+    }
+}
+
+/// Rows for the Statistics submenu, today first.
+pub fn menu_rows(events: &[Event]) -> Vec<String> {
+    let now = Local::now();
+    daily(events, MENU_DAYS, &now)
+        .into_iter()
+        .map(|day| {
+            t!(
+                "stats-menu-day",
+                day = day_label(day.date, now.date_naive()),
+                enabled = crate::daemon::format_duration(day.enabled),
+                restarts = day.restarts,
+                display = day.display_restarts,
+            )
+        })
+        .collect()
+}
+
+/// Table for `lapsus-menubar stats`, newest day first.
+pub fn report(events: &[Event], days: usize) -> String {
+    let now = Local::now();
+    let summaries = daily(events, days, &now);
+    let headers = [
+        t!("stats-column-date"),
+        t!("stats-column-enabled"),
+        t!("stats-column-restarts"),
+        t!("stats-column-display"),
+        t!("stats-column-crashes"),
+    ];
+    let mut rows = vec![headers.to_vec()];
+    for day in &summaries {
+        rows.push(vec![
+            day.date.format("%Y-%m-%d").to_string(),
+            crate::daemon::format_duration(day.enabled),
+            day.restarts.to_string(),
+            day.display_restarts.to_string(),
+            day.crashes.to_string(),
+        ]);
+    }
+    let total = summaries.iter().map(|day| day.enabled).sum();
+    rows.push(vec![
+        t!("stats-total"),
+        crate::daemon::format_duration(total),
+        summaries.iter().map(|day| day.restarts).sum::<u32>().to_string(),
+        summaries.iter().map(|day| day.display_restarts).sum::<u32>().to_string(),
+        summaries.iter().map(|day| day.crashes).sum::<u32>().to_string(),
+    ]);
+
+    let widths: Vec<usize> = (0..headers.len())
+        .map(|column| rows.iter().map(|row| row[column].chars().count()).max().unwrap_or(0))
+        .collect();
+    let mut table = String::new();
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (cell, &width))| match column {
+                0 => format!("{:<width$}", cell),
+                _ => format!("{:>width$}", cell),
+            })
+            .collect();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(date: &str, time: &str) -> i64 {
+        format!("{}T{}Z", date, time).parse::<DateTime<Utc>>().unwrap().timestamp()
+    }
+
+    fn event(at: i64, kind: EventKind, source: Source) -> Event {
+        Event { at, kind, source }
+    }
+
+    #[test]
+    fn splits_enabled_time_across_days() {
+        let events = [
+            event(at("2026-03-01", "22:00:00"), EventKind::Enabled, Source::User),
+            event(at("2026-03-02", "01:30:00"), EventKind::Disabled, Source::Schedule),
+            event(at("2026-03-02", "09:00:00"), EventKind::Enabled, Source::Schedule),
+        ];
+        let now = at("2026-03-02", "10:15:00");
+        let days = daily(&events, 3, &Utc.timestamp_opt(now, 0).unwrap());
+
+        let hours = |day: &DaySummary| day.enabled.as_secs_f64() / 3600.0;
+        assert_eq!(days[0].date.to_string(), "2026-03-02");
+        assert_eq!(hours(&days[0]), 1.5 + 1.25);
+        assert_eq!(hours(&days[1]), 2.0);
+        assert_eq!(hours(&days[2]), 0.0);
+    }
+
+    #[test]
+    fn counts_restarts_and_crashes() {
+        let day = "2026-03-02";
+        let events = [
+            event(at(day, "08:00:00"), EventKind::Enabled, Source::User),
+            event(at(day, "09:00:00"), EventKind::Restarted, Source::DisplaySwitch),
+            event(at(day, "09:30:00"), EventKind::Restarted, Source::DisplaySwitch),
+            event(at(day, "10:00:00"), EventKind::Restarted, Source::User),
+            event(at(day, "11:00:00"), EventKind::Crashed, Source::Supervisor),
+            event(at(day, "11:00:01"), EventKind::Enabled, Source::Supervisor),
+        ];
+        let now = Utc.timestamp_opt(at(day, "12:00:00"), 0).unwrap();
+        let today = &daily(&events, 1, &now)[0];
+
+        assert_eq!(today.restarts, 4);
+        assert_eq!(today.display_restarts, 2);
+        assert_eq!(today.crashes, 1);
+        // Down for the second between the crash and the restart
+        assert_eq!(today.enabled, Duration::from_secs(4 * 3600 - 1));
+    }
+
+    #[test]
+    fn records_what_launch_finds() {
+        let now = Local::now().timestamp();
+        let mut log = EventLog::in_memory();
+        log.observe(None);
+        assert!(log.events().is_empty());
+
+        log.observe(Some(now - 5));
+        log.record(EventKind::Restarted, Source::User);
+        log.observe(Some(now - 5));
+        assert_eq!(log.events().len(), 2);
+        assert_eq!(log.events()[0], event(now - 5, EventKind::Enabled, Source::Launch));
+    }
+
+    #[test]
+    fn downtime_starts_at_the_last_heartbeat() {
+        let mut log = EventLog::in_memory();
+        log.record_at(1_000, EventKind::Enabled, Source::User);
+        log.seen = 5_000;
+
+        // Found stopped, e.g. after a shutdown
+        log.observe(None);
+        assert_eq!(log.events()[1], event(5_000, EventKind::Disabled, Source::Launch));
+
+        // Found running, started after the app last looked
+        let mut log = EventLog::in_memory();
+        log.record_at(1_000, EventKind::Enabled, Source::User);
+        log.seen = 5_000;
+        log.observe(Some(9_000));
+        assert_eq!(
+            log.events()[1..],
+            [
+                event(5_000, EventKind::Disabled, Source::Launch),
+                event(9_000, EventKind::Enabled, Source::Launch),
+            ]
+        );
+
+        // Running all along, e.g. after Quit (Leave Lapsus Running)
+        log.observe(Some(8_000));
+        assert_eq!(log.events().len(), 3);
+    }
+
+    #[test]
+    fn heartbeat_is_saved_once_a_minute() {
+        let path = std::env::temp_dir().join(format!("lapsus-heartbeat-{}.jsonl", std::process::id()));
+        let mut log = EventLog::open(path.clone());
+        log.heartbeat();
+        let first = log.seen;
+        assert!(first > 0);
+        log.seen -= 30;
+        log.heartbeat();
+        assert_eq!(log.seen, first - 30);
+
+        assert_eq!(EventLog::open(path.clone()).seen, first);
+        let _ = fs::remove_file(seen_path(&path));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn drops_unreadable_lines() {
+        let path = std::env::temp_dir().join(format!("lapsus-events-{}.jsonl", std::process::id()));
+        let now = Local::now().timestamp();
+        let kept = format!(r#"{{"at":{},"kind":"enabled","source":"user"}}"#, now);
+        let old = r#"{"at":0,"kind":"disabled","source":"user"}"#;
+        fs::write(&path, format!("{}\nnot json\n{}\n", old, kept)).unwrap();
+
+        let log = EventLog::open(path.clone());
+        assert_eq!(log.events().len(), 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("{}\n", kept));
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Give up after this many crash or health restarts within `RESTART_WINDOW`.
+const MAX_RESTARTS: usize = 3;
+const RESTART_WINDOW: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupervisorAction {
+    Restart,
+    /// Crashing too often; leave it stopped. After a failed health check,
+    /// leave it as it is
+    GiveUp,
+}
+
+/// Notices lapsus_rust exiting without being asked to, or failing a health
+/// check, and decides whether to bring it back.
+pub struct Supervisor {
+    /// True after we started (or adopted) lapsus_rust and until we stop it
+    wanted: bool,
+    restarts: VecDeque<Instant>,
+}
+
+impl Supervisor {
+    pub fn new(running: bool) -> Self {
+        Self {
+            wanted: running,
+            restarts: VecDeque::new(),
+        }
+    }
+
+    /// Record an intentional start (`true`) or stop.
+    pub fn set_wanted(&mut self, wanted: bool) {
+        self.wanted = wanted;
+    }
+
+    /// Called with consecutive daemon scans.
+    pub fn on_poll(&mut self, was_running: bool, running: bool, now: Instant) -> Option<SupervisorAction> {
+        if !(self.wanted && was_running && !running) {
+            return None;
+        }
+        let action = self.budget(now);
+        if action == SupervisorAction::GiveUp {
+            self.wanted = false;
+        }
+        Some(action)
+    }
+
+    /// Called when a running lapsus_rust has just failed a health check.
+    pub fn on_unhealthy(&mut self, now: Instant) -> SupervisorAction {
+        self.budget(now)
+    }
+
+    /// Restart unless the restarts in the window are used up.
+    fn budget(&mut self, now: Instant) -> SupervisorAction {
+        while self.restarts.front().is_some_and(|&at| now.duration_since(at) >= RESTART_WINDOW) {
+            self.restarts.pop_front();
+        }
+        if self.restarts.len() >= MAX_RESTARTS {
+            return SupervisorAction::GiveUp;
+        }
+        self.restarts.push_back(now);
+        SupervisorAction::Restart
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restarts_a_crash_only_when_wanted() {
+        let mut supervisor = Supervisor::new(false);
+        let now = Instant::now();
+        assert_eq!(supervisor.on_poll(true, false, now), None);
+
+        supervisor.set_wanted(true);
+        assert_eq!(supervisor.on_poll(true, true, now), None);
+        assert_eq!(supervisor.on_poll(false, false, now), None);
+        assert_eq!(supervisor.on_poll(true, false, now), Some(SupervisorAction::Restart));
+    }
+
+    #[test]
+    fn gives_up_after_max_restarts_in_the_window() {
+        let mut supervisor = Supervisor::new(true);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        for i in 0..MAX_RESTARTS as u64 {
+            assert_eq!(supervisor.on_poll(true, false, at(i * 10)), Some(SupervisorAction::Restart));
+        }
+        assert_eq!(supervisor.on_poll(true, false, at(40)), Some(SupervisorAction::GiveUp));
+        // Left stopped: a later exit isn't acted on until it's wanted again
+        assert_eq!(supervisor.on_poll(true, false, at(50)), None);
+    }
+
+    #[test]
+    fn budget_resets_once_the_window_expires() {
+        let mut supervisor = Supervisor::new(true);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        for secs in [0, 10, 20] {
+            assert_eq!(supervisor.on_unhealthy(at(secs)), SupervisorAction::Restart);
+        }
+        assert_eq!(supervisor.on_unhealthy(at(299)), SupervisorAction::GiveUp);
+        // Health restarts share the budget with crashes
+        assert_eq!(supervisor.on_poll(true, false, at(299)), Some(SupervisorAction::GiveUp));
+
+        supervisor.set_wanted(true);
+        let expired = RESTART_WINDOW.as_secs();
+        assert_eq!(supervisor.on_poll(true, false, at(expired)), Some(SupervisorAction::Restart));
+        assert_eq!(supervisor.on_unhealthy(at(expired + 10)), SupervisorAction::Restart);
+        assert_eq!(supervisor.on_unhealthy(at(expired + 20)), SupervisorAction::Restart);
+        assert_eq!(supervisor.on_unhealthy(at(expired + 30)), SupervisorAction::GiveUp);
+    }
+}
@@ -0,0 +1,123 @@
+use image::{Rgba, RgbaImage};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg;
+
+/// Rasterizes an SVG document to exactly `height` pixels tall, keeping its
+/// aspect ratio.
+pub fn render(data: &[u8], height: u32) -> Result<RgbaImage, Box<dyn std::error::Error>> {
+    let tree = usvg::Tree::from_data(data, &usvg::Options::default())?;
+    let size = tree.size();
+    let scale = height as f32 / size.height();
+    let width = (size.width() * scale).round().max(1.0) as u32;
+
+    let mut pixmap = Pixmap::new(width, height).ok_or("icon size is zero")?;
+    resvg::render(&tree, Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+
+    // tiny-skia stores premultiplied alpha; tray icons want it straight
+    let pixels = pixmap.pixels();
+    Ok(RgbaImage::from_fn(width, height, |x, y| {
+        let color = pixels[(y * width + x) as usize].demultiply();
+        Rgba([color.red(), color.green(), color.blue(), color.alpha()])
+    }))
+}
+
+/// The basic CSS color names, for tints that don't need an exact shade.
+const NAMED_COLORS: [(&str, [u8; 3]); 17] = [
+    ("black", [0x00, 0x00, 0x00]),
+    ("silver", [0xc0, 0xc0, 0xc0]),
+    ("gray", [0x80, 0x80, 0x80]),
+    ("white", [0xff, 0xff, 0xff]),
+    ("maroon", [0x80, 0x00, 0x00]),
+    ("red", [0xff, 0x00, 0x00]),
+    ("purple", [0x80, 0x00, 0x80]),
+    ("fuchsia", [0xff, 0x00, 0xff]),
+    ("green", [0x00, 0x80, 0x00]),
+    ("lime", [0x00, 0xff, 0x00]),
+    ("olive", [0x80, 0x80, 0x00]),
+    ("yellow", [0xff, 0xff, 0x00]),
+    ("navy", [0x00, 0x00, 0x80]),
+    ("blue", [0x00, 0x00, 0xff]),
+    ("teal", [0x00, 0x80, 0x80]),
+    ("aqua", [0x00, 0xff, 0xff]),
+    ("orange", [0xff, 0xa5, 0x00]),
+];
+
+/// Parses "#RRGGBB", the short form "#RGB" (the "#" is optional in both),
+/// or a basic CSS color name such as "orange".
+pub fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let color = color.trim();
+    if let Some((_, rgb)) = NAMED_COLORS.iter().find(|(name, _)| name.eq_ignore_ascii_case(color)) {
+        return Some(*rgb);
+    }
+
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match hex.len() {
+        6 => Some([channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?]),
+        // "#f80" is "#ff8800"
+        3 => Some(std::array::from_fn(|i| channel(&hex[i..i + 1]).unwrap_or(0) * 0x11)),
+        _ => None,
+    }
+}
+
+/// Paints every pixel `color`, keeping its alpha. Icon sources are
+/// monochrome, so this recolors the shape.
+pub fn tinted(image: &RgbaImage, color: [u8; 3]) -> RgbaImage {
+    let mut image = image.clone();
+    for pixel in image.pixels_mut() {
+        pixel[0] = color[0];
+        pixel[1] = color[1];
+        pixel[2] = color[2];
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_and_named_colors() {
+        assert_eq!(parse_color("#0a84ff"), Some([0x0a, 0x84, 0xff]));
+        assert_eq!(parse_color(" FF3B30 "), Some([0xff, 0x3b, 0x30]));
+        assert_eq!(parse_color("#f80"), Some([0xff, 0x88, 0x00]));
+        assert_eq!(parse_color("abc"), Some([0xaa, 0xbb, 0xcc]));
+        assert_eq!(parse_color("orange"), Some([0xff, 0xa5, 0x00]));
+        assert_eq!(parse_color("Red"), Some([0xff, 0x00, 0x00]));
+    }
+
+    #[test]
+    fn rejects_other_colors() {
+        for color in ["", "#", "#12345", "#1234567", "#ggg", "#+1+2+3", "#ééé", "rgb(1, 2, 3)", "chartreuse"] {
+            assert_eq!(parse_color(color), None, "{:?}", color);
+        }
+    }
+
+    #[test]
+    fn renders_at_the_requested_height() {
+        let wide = br#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10"><rect width="20" height="10"/></svg>"#;
+        for height in [16, 22, 44, 66] {
+            let image = render(wide, height).unwrap();
+            assert_eq!(image.dimensions(), (height * 2, height));
+            // Filled edge to edge, with straight (not premultiplied) alpha
+            assert_eq!(*image.get_pixel(height, height / 2), Rgba([0, 0, 0, 255]));
+        }
+
+        let icon = render(include_bytes!("../icons/cursor_enabled.svg"), 36).unwrap();
+        assert_eq!(icon.height(), 36);
+        assert!(icon.pixels().any(|pixel| pixel[3] > 0));
+
+        assert!(render(b"not an svg", 22).is_err());
+    }
+
+    #[test]
+    fn tint_keeps_the_shape() {
+        let image = RgbaImage::from_fn(2, 1, |x, _| Rgba([0, 0, 0, if x == 0 { 255 } else { 0 }]));
+        let tinted = tinted(&image, [0xff, 0x3b, 0x30]);
+        assert_eq!(*tinted.get_pixel(0, 0), Rgba([0xff, 0x3b, 0x30, 255]));
+        assert_eq!(tinted.get_pixel(1, 0)[3], 0);
+    }
+}
@@ -0,0 +1,345 @@
+use serde::{Deserialize, Serialize};
+use tray_icon::menu::accelerator::Accelerator;
+use tray_icon::menu::{CheckMenuItem, Menu, MenuId, MenuItem, PredefinedMenuItem, Submenu};
+
+use crate::i18n::t;
+use crate::logging::LogLevel;
+use crate::pause::PAUSE_MINUTES;
+use crate::stats::MENU_DAYS;
+use crate::QuitBehavior;
+
+/// What a primary (left) click on the tray icon does. The menu is always
+/// available on secondary click.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClickAction {
+    Toggle,
+    #[default]
+    OpenMenu,
+    Restart,
+}
+
+impl ClickAction {
+    /// The menu command a click runs, or `None` when it just opens the menu.
+    pub fn command(self) -> Option<&'static str> {
+        match self {
+            ClickAction::Toggle => Some("toggle"),
+            ClickAction::Restart => Some("restart"),
+            ClickAction::OpenMenu => None,
+        }
+    }
+}
+
+/// Everything the menu displays, derived from the daemon and app state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuView {
+    pub status: String,
+    pub running: bool,
+    /// Disabled info rows under the toggle: rule, schedule, pause countdown
+    pub notes: Vec<String>,
+    pub start_at_login: bool,
+    /// What plain Quit does; the alternate item does the other
+    pub quit_behavior: QuitBehavior,
+    pub log_level: LogLevel,
+    /// Statistics rows, one per day, today first
+    pub statistics: Vec<String>,
+}
+
+/// Index of the first note row: status, separator, toggle, "Pause for".
+const NOTES_POSITION: usize = 4;
+
+/// The tray menu, built once. `update` changes text, enabled and checked
+/// state in place, so an open menu stays open and nothing rescans processes.
+pub struct TrayMenu {
+    pub menu: Menu,
+    status_item: MenuItem,
+    toggle_item: MenuItem,
+    pause_menu: Submenu,
+    note_items: Vec<MenuItem>,
+    start_at_login: CheckMenuItem,
+    statistics: Vec<MenuItem>,
+    log_levels: Vec<(LogLevel, CheckMenuItem)>,
+    quit_alternate: MenuItem,
+    shown: Option<MenuView>,
+}
+
+impl TrayMenu {
+    pub fn new(view: &MenuView) -> Result<Self, Box<dyn std::error::Error>> {
+        let menu = Menu::new();
+
+        // Status header: state, PID, uptime, CPU and memory
+        let status_item = MenuItem::new("", false, None);
+        menu.append(&status_item)?;
+        menu.append(&PredefinedMenuItem::separator())?;
+
+        // Enable/Disable; the handler reads `toggle_command` to know which
+        let toggle_item = MenuItem::with_id(MenuId::new("toggle"), "", true, None);
+        menu.append(&toggle_item)?;
+
+        // Timed disable
+        let pause_menu = Submenu::new(t!("menu-pause-for"), true);
+        for minutes in PAUSE_MINUTES {
+            let label = if minutes == 60 {
+                t!("menu-pause-hour")
+            } else {
+                t!("menu-pause-minutes", minutes = minutes)
+            };
+            let pause_item = MenuItem::with_id(
+                MenuId::new(format!("pause_{}", minutes)),
+                label,
+                true,
+                None
+            );
+            pause_menu.append(&pause_item)?;
+        }
+        menu.append(&pause_menu)?;
+
+        menu.append(&PredefinedMenuItem::separator())?;
+
+        // Start at Login checkbox
+        let start_at_login = CheckMenuItem::with_id(
+            MenuId::new("start_at_login"),
+            t!("menu-start-at-login"),
+            true,
+            false,
+            None
+        );
+        menu.append(&start_at_login)?;
+
+        // Preferences window
+        let preferences_item = MenuItem::with_id(MenuId::new("preferences"), t!("menu-preferences"), true, None);
+        menu.append(&preferences_item)?;
+
+        // Enabled time and restarts for the last days
+        let statistics_menu = Submenu::new(t!("menu-statistics"), true);
+        let mut statistics = Vec::new();
+        for _ in 0..MENU_DAYS {
+            let item = MenuItem::new("", false, None);
+            statistics_menu.append(&item)?;
+            statistics.push(item);
+        }
+        menu.append(&statistics_menu)?;
+
+        // Log level, checked like radio items
+        let log_menu = Submenu::new(t!("menu-log-level"), true);
+        let mut log_levels = Vec::new();
+        for level in LogLevel::ALL {
+            let label = match level {
+                LogLevel::Error => t!("menu-log-error"),
+                LogLevel::Warn => t!("menu-log-warn"),
+                LogLevel::Info => t!("menu-log-info"),
+                LogLevel::Debug => t!("menu-log-debug"),
+                LogLevel::Trace => t!("menu-log-trace"),
+            };
+            let item = CheckMenuItem::with_id(
+                MenuId::new(format!("log_level_{}", level.id())),
+                label,
+                true,
+                false,
+                None
+            );
+            log_menu.append(&item)?;
+            log_levels.push((level, item));
+        }
+        menu.append(&log_menu)?;
+
+        menu.append(&PredefinedMenuItem::separator())?;
+
+        // About
+        let about_item = MenuItem::with_id(MenuId::new("about"), t!("menu-about"), true, None);
+        menu.append(&about_item)?;
+
+        // Quit, following `quit_behavior`, and the alternate that stops
+        // lapsus_rust or leaves it running instead
+        let quit_item = MenuItem::with_id(MenuId::new("quit"), t!("menu-quit"), true, None);
+        menu.append(&quit_item)?;
+        let quit_alternate = MenuItem::with_id(MenuId::new("quit_alternate"), "", true, None);
+        menu.append(&quit_alternate)?;
+
+        let mut tray_menu = Self {
+            menu,
+            status_item,
+            toggle_item,
+            pause_menu,
+            note_items: Vec::new(),
+            start_at_login,
+            statistics,
+            log_levels,
+            quit_alternate,
+            shown: None,
+        };
+        tray_menu.update(view)?;
+        Ok(tray_menu)
+    }
+
+    /// Bring the menu in line with `view`, touching only what changed.
+    pub fn update(&mut self, view: &MenuView) -> Result<(), Box<dyn std::error::Error>> {
+        let shown = self.shown.take();
+
+        if shown.as_ref().is_none_or(|s| s.status != view.status) {
+            self.status_item.set_text(&view.status);
+        }
+        if shown.as_ref().is_none_or(|s| s.running != view.running) {
+            self.toggle_item.set_text(if view.running { t!("menu-disable") } else { t!("menu-enable") });
+            self.pause_menu.set_enabled(view.running);
+            // Same as plain Quit while nothing is running
+            self.quit_alternate.set_enabled(view.running);
+        }
+        if shown.as_ref().is_none_or(|s| s.statistics != view.statistics) {
+            for (item, row) in self.statistics.iter().zip(&view.statistics) {
+                item.set_text(row);
+            }
+        }
+        // Always: clicking a check item toggles it before the handler runs
+        self.start_at_login.set_checked(view.start_at_login);
+        for (level, item) in &self.log_levels {
+            item.set_checked(*level == view.log_level);
+        }
+        if shown.as_ref().is_none_or(|s| s.quit_behavior != view.quit_behavior) {
+            self.quit_alternate.set_text(match view.quit_behavior.other() {
+                QuitBehavior::Stop => t!("menu-quit-stop"),
+                QuitBehavior::LeaveRunning => t!("menu-quit-leave-running"),
+            });
+        }
+
+        // Grow or shrink the note rows, then retitle them
+        while self.note_items.len() > view.notes.len() {
+            let item = self.note_items.pop().unwrap();
+            self.menu.remove(&item)?;
+        }
+        while self.note_items.len() < view.notes.len() {
+            let item = MenuItem::new("", false, None);
+            self.menu.insert(&item, NOTES_POSITION + self.note_items.len())?;
+            self.note_items.push(item);
+        }
+        for (item, note) in self.note_items.iter().zip(&view.notes) {
+            if item.text() != *note {
+                item.set_text(note);
+            }
+        }
+
+        self.shown = Some(view.clone());
+        Ok(())
+    }
+
+    /// Set only the status header, e.g. right before the menu opens.
+    pub fn set_status(&mut self, status: String) {
+        self.status_item.set_text(&status);
+        if let Some(shown) = &mut self.shown {
+            shown.status = status;
+        }
+    }
+
+    /// Show the global toggle hotkey next to Enable/Disable.
+    pub fn set_toggle_accelerator(
+        &self,
+        accelerator: Option<Accelerator>
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.toggle_item.set_accelerator(accelerator)?;
+        Ok(())
+    }
+
+    /// The command the toggle item stands for as currently displayed.
+    pub fn toggle_command(&self) -> &'static str {
+        match &self.shown {
+            Some(view) if view.running => "disable",
+            _ => "enable",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tray_icon::menu::MenuItemKind;
+
+    fn stopped() -> MenuView {
+        MenuView {
+            status: "Stopped".to_string(),
+            running: false,
+            notes: Vec::new(),
+            start_at_login: false,
+            quit_behavior: QuitBehavior::Stop,
+            log_level: LogLevel::Info,
+            statistics: vec!["Today: 1h".to_string()],
+        }
+    }
+
+    fn checked_levels(tray_menu: &TrayMenu) -> Vec<LogLevel> {
+        tray_menu.log_levels.iter().filter(|(_, item)| item.is_checked()).map(|(level, _)| *level).collect()
+    }
+
+    #[test]
+    fn items_follow_the_view() {
+        let mut tray_menu = TrayMenu::new(&stopped()).unwrap();
+        assert_eq!(tray_menu.status_item.text(), "Stopped");
+        assert_eq!(tray_menu.toggle_item.text(), "Enable Lapsus");
+        assert_eq!(tray_menu.toggle_command(), "enable");
+        assert!(!tray_menu.pause_menu.is_enabled());
+        assert!(!tray_menu.start_at_login.is_checked());
+        assert_eq!(tray_menu.statistics[0].text(), "Today: 1h");
+        assert_eq!(checked_levels(&tray_menu), vec![LogLevel::Info]);
+        assert_eq!(tray_menu.quit_alternate.text(), "Quit (Leave Lapsus Running)");
+        assert!(!tray_menu.quit_alternate.is_enabled());
+
+        let running = MenuView {
+            status: "Running · PID 4512".to_string(),
+            running: true,
+            start_at_login: true,
+            quit_behavior: QuitBehavior::LeaveRunning,
+            log_level: LogLevel::Debug,
+            ..stopped()
+        };
+        tray_menu.update(&running).unwrap();
+        assert_eq!(tray_menu.status_item.text(), "Running · PID 4512");
+        assert_eq!(tray_menu.toggle_item.text(), "Disable Lapsus");
+        assert_eq!(tray_menu.toggle_command(), "disable");
+        assert!(tray_menu.pause_menu.is_enabled());
+        assert!(tray_menu.start_at_login.is_checked());
+        assert_eq!(checked_levels(&tray_menu), vec![LogLevel::Debug]);
+        assert_eq!(tray_menu.quit_alternate.text(), "Quit and Stop Lapsus");
+        assert!(tray_menu.quit_alternate.is_enabled());
+    }
+
+    #[test]
+    fn check_items_are_reset_even_when_the_view_is_unchanged() {
+        let mut tray_menu = TrayMenu::new(&stopped()).unwrap();
+        // As a click does before the handler runs
+        tray_menu.start_at_login.set_checked(true);
+        tray_menu.log_levels[0].1.set_checked(true);
+
+        tray_menu.update(&stopped()).unwrap();
+        assert!(!tray_menu.start_at_login.is_checked());
+        assert_eq!(checked_levels(&tray_menu), vec![LogLevel::Info]);
+    }
+
+    #[test]
+    fn note_rows_sit_under_the_toggle() {
+        let mut tray_menu = TrayMenu::new(&stopped()).unwrap();
+        let notes = |tray_menu: &TrayMenu, count: usize| -> Vec<(String, bool)> {
+            let items = tray_menu.menu.items();
+            assert!(matches!(items[NOTES_POSITION + count], MenuItemKind::Predefined(_)));
+            items[NOTES_POSITION..NOTES_POSITION + count]
+                .iter()
+                .map(|item| {
+                    let item = item.as_menuitem().expect("a note row");
+                    (item.text(), item.is_enabled())
+                })
+                .collect()
+        };
+        assert!(notes(&tray_menu, 0).is_empty());
+
+        let with_notes = |texts: &[&str]| MenuView {
+            notes: texts.iter().map(|text| text.to_string()).collect(),
+            ..stopped()
+        };
+        tray_menu.update(&with_notes(&["Rule: Zoom", "Paused for 12 more minutes"])).unwrap();
+        assert_eq!(
+            notes(&tray_menu, 2),
+            vec![("Rule: Zoom".to_string(), false), ("Paused for 12 more minutes".to_string(), false)]
+        );
+
+        tray_menu.update(&with_notes(&["Scheduled until 18:00"])).unwrap();
+        assert_eq!(notes(&tray_menu, 1), vec![("Scheduled until 18:00".to_string(), false)]);
+    }
+}